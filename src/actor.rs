@@ -1,14 +1,25 @@
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::ops::Add;
 
 use crate::{
+    combat::{remove_dead_actors, AttackEvent, AttackKind},
+    fov::{remember_enemies, Viewshed},
+    game_state::{AppState, GameplaySet, LevelSet},
+    item::{Equipment, Inventory},
     level_generation::map::{Map, ViewStatus},
+    lighting::LightSource,
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction},
     position::{Position, PositionDelta},
-    sprite_atlas::{SpriteAtlas, SpriteIndex},
+    rng::GameRng,
+    sprite_atlas::{Renderable, SpriteIndex},
+    status::{StatusEffects, StatusKind},
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, States)]
-enum TurnState {
+pub enum TurnState {
     #[default]
     Player,
     Enemy,
@@ -18,40 +29,104 @@ pub struct ActorPlugin;
 impl Plugin for ActorPlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<TurnState>()
-            .add_systems(PostStartup, (spawn_player, spawn_enemies))
+            .init_resource::<TurnCount>()
+            .init_resource::<EnemyRounds>()
             .add_systems(
-                Update,
-                clear_moved_markers.run_if(state_changed::<TurnState>()),
+                OnEnter(AppState::LoadingLevel),
+                (spawn_player, spawn_enemies).in_set(LevelSet::Spawn),
             )
             .add_systems(
-                Update,
-                player_movement.run_if(state_exists_and_equals(TurnState::Player)),
+                OnEnter(TurnState::Player),
+                (advance_turn_count, regenerate_health).chain(),
             )
-            .add_systems(Update, update_dormant_enemies)
+            .add_systems(OnEnter(TurnState::Enemy), start_enemy_phase)
             .add_systems(
                 Update,
-                (select_next_enemy_to_move, enemy_movement)
-                    .chain()
-                    .run_if(state_exists_and_equals(TurnState::Enemy)),
-            )
-            .add_systems(
-                PostUpdate,
-                (update_transform, center_camera_on_player).chain(),
+                (
+                    clear_moved_markers.run_if(state_changed::<TurnState>()),
+                    (player_movement, wait)
+                        .after(take_player_action)
+                        .run_if(state_exists_and_equals(TurnState::Player)),
+                    update_dormant_enemies.after(remember_enemies),
+                    //after the dead are found, so nothing killed this frame is picked
+                    (
+                        select_next_enemy_to_move.after(remove_dead_actors),
+                        enemy_movement,
+                    )
+                        .chain()
+                        .run_if(state_exists_and_equals(TurnState::Enemy)),
+                )
+                    .in_set(GameplaySet),
             );
     }
 }
 
+//Number of player turns taken so far
+#[derive(Resource, Default)]
+pub struct TurnCount(pub u32);
+
+//Rounds of enemy movement left before control returns to the player
+#[derive(Resource, Default)]
+struct EnemyRounds(u32);
+
 #[derive(Component)]
 pub struct Player;
 
 #[derive(Component)]
 pub struct Enemy;
 
-#[derive(Component)]
+//Which bundle an enemy was spawned from, so a saved game can spawn it again
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyKind {
+    Bat,
+}
+
+impl EnemyKind {
+    pub fn spawn(self, commands: &mut Commands, position: Position) -> Entity {
+        match self {
+            EnemyKind::Bat => commands.spawn(bat_bundle(position)).id(),
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Copy)]
 pub struct Actor {
-    _health: f32,
+    pub health: f32,
+    pub max_health: f32,
+    pub base_stats: CombatStats,
 }
 
+//Effective stats, recalculated from the actor's base stats whenever its equipment changes
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct CombatStats {
+    pub attack: f32,
+    pub defense: f32,
+}
+
+impl Add for CombatStats {
+    type Output = CombatStats;
+
+    fn add(self, rhs: CombatStats) -> CombatStats {
+        CombatStats {
+            attack: self.attack + rhs.attack,
+            defense: self.defense + rhs.defense,
+        }
+    }
+}
+
+const PLAYER_STATS: CombatStats = CombatStats {
+    attack: 5.,
+    defense: 0.,
+};
+
+//Turns it takes the player to regain a point of health
+const REGENERATION_TURNS: u32 = 5;
+
+const BAT_STATS: CombatStats = CombatStats {
+    attack: 2.,
+    defense: 0.,
+};
+
 #[derive(Component)]
 pub struct Dormant;
 
@@ -61,114 +136,175 @@ pub struct SelectedToMove;
 #[derive(Component)]
 pub struct MovedThisTurn;
 
-#[derive(Component)]
-pub struct Movement {
-    just_moved: bool,
+fn advance_turn_count(mut turn_count: ResMut<TurnCount>) {
+    turn_count.0 += 1;
 }
 
-#[allow(clippy::type_complexity)]
-fn center_camera_on_player(
-    mut player_query: Query<(&mut Movement, &Transform), (With<Player>, Changed<Movement>)>,
-    mut camera_query: Query<&mut Transform, (Without<Player>, With<Camera>)>,
+//The player slowly heals on their own, a point of health every so many turns
+fn regenerate_health(
+    mut player_query: Query<&mut Actor, With<Player>>,
+    turn_count: Res<TurnCount>,
 ) {
-    if let Ok((mut player_movement, player_transform)) = player_query.get_single_mut() {
-        if player_movement.just_moved {
-            let mut camera_transform = camera_query.single_mut();
-            camera_transform.translation.x = player_transform.translation.x;
-            camera_transform.translation.y = player_transform.translation.y;
-            player_movement.just_moved = false;
+    if !turn_count.0.is_multiple_of(REGENERATION_TURNS) {
+        return;
+    }
+    for mut actor in player_query.iter_mut() {
+        if actor.health > 0. && actor.health < actor.max_health {
+            actor.health = (actor.health + 1.).min(actor.max_health);
         }
     }
 }
 
+fn wait(mut actions: EventReader<PlayerAction>, mut next_state: ResMut<NextState<TurnState>>) {
+    if actions.iter().any(|action| *action == PlayerAction::Wait) {
+        next_state.set(TurnState::Enemy);
+    }
+}
+
+//A slowed player gives enemies an extra round, a hasted one only lets them act every other turn
+fn start_enemy_phase(
+    player_query: Query<&StatusEffects, With<Player>>,
+    turn_count: Res<TurnCount>,
+    mut rounds: ResMut<EnemyRounds>,
+) {
+    let status_effects = player_query.single();
+    rounds.0 = if status_effects.has(StatusKind::Hasted) {
+        turn_count.0 % 2
+    } else if status_effects.has(StatusKind::Slowed) {
+        2
+    } else {
+        1
+    };
+}
+
+#[allow(clippy::type_complexity)]
 fn player_movement(
-    mut player_query: Query<(&mut Movement, &mut Position), (With<Player>, Without<Enemy>)>,
-    enemy_query: Query<&Position, (With<Enemy>, Without<Player>)>,
+    mut player_query: Query<
+        (Entity, &mut Position, &StatusEffects),
+        (With<Player>, Without<Enemy>),
+    >,
+    enemy_query: Query<(Entity, &Position), (With<Enemy>, Without<Player>)>,
     map: Res<Map>,
-    keyboard: Res<Input<KeyCode>>,
+    mut actions: EventReader<PlayerAction>,
+    mut rng: ResMut<GameRng>,
+    mut attacks: EventWriter<AttackEvent>,
     mut next_state: ResMut<NextState<TurnState>>,
 ) {
-    let mut delta = PositionDelta::new(0, 0);
-    if keyboard.just_pressed(KeyCode::W) {
-        delta.y += 1;
-    }
-    if keyboard.just_pressed(KeyCode::A) {
-        delta.x -= 1;
-    }
-    if keyboard.just_pressed(KeyCode::S) {
-        delta.y -= 1;
-    }
-    if keyboard.just_pressed(KeyCode::D) {
-        delta.x += 1;
-    }
+    let delta = actions.iter().find_map(|action| match action {
+        PlayerAction::Move(delta) => Some(*delta),
+        _ => None,
+    });
 
-    if delta.x != 0 || delta.y != 0 {
-        let (mut player_movement, mut player_position) = player_query.get_single_mut().unwrap();
+    if let Some(delta) = delta {
+        let (player, mut player_position, status_effects) = player_query.get_single_mut().unwrap();
+        let delta = status_effects.adjust_movement(delta, &mut rng.0);
         let new_position = *player_position + delta;
 
-        //check that player won't collide with any enemies
-        if enemy_query
+        //moving into an enemy attacks it
+        if let Some((enemy, _)) = enemy_query
             .iter()
-            .any(|enemy_position| &new_position == enemy_position)
+            .find(|(_, enemy_position)| &new_position == *enemy_position)
         {
+            attacks.send(AttackEvent {
+                attacker: player,
+                defender: enemy,
+                kind: AttackKind::Melee,
+            });
+            next_state.set(TurnState::Enemy);
             return;
         }
 
         if let Some(tile) = map.get(new_position.x, new_position.y) {
             if tile.passable {
                 *player_position = new_position;
-                player_movement.just_moved = true;
                 next_state.set(TurnState::Enemy);
             }
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn select_next_enemy_to_move(
     mut commands: Commands,
     selection_query: Query<(), With<SelectedToMove>>,
-    enemy_query: Query<Entity, (With<Enemy>, Without<MovedThisTurn>, Without<Dormant>)>,
+    enemy_query: Query<(Entity, &Actor), (With<Enemy>, Without<MovedThisTurn>, Without<Dormant>)>,
+    moved_query: Query<Entity, With<MovedThisTurn>>,
+    mut rounds: ResMut<EnemyRounds>,
     mut next_state: ResMut<NextState<TurnState>>,
 ) {
     if !selection_query.is_empty() {
         return;
     }
-    for entity in enemy_query.iter() {
-        commands.entity(entity).insert(SelectedToMove);
+    if rounds.0 > 0 {
+        //enemies killed this frame are only despawned once commands are applied
+        if let Some((entity, _)) = enemy_query.iter().find(|(_, actor)| actor.health > 0.) {
+            commands.entity(entity).insert(SelectedToMove);
+            return;
+        }
+    }
+    if rounds.0 > 1 {
+        rounds.0 -= 1;
+        moved_query.iter().for_each(|entity| {
+            commands.entity(entity).remove::<MovedThisTurn>();
+        });
         return;
     }
     next_state.set(TurnState::Player);
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn enemy_movement(
     mut commands: Commands,
-    mut target_query: Query<(Entity, &mut Position), With<SelectedToMove>>,
+    mut target_query: Query<(Entity, &mut Position, &StatusEffects), With<SelectedToMove>>,
     actor_query: Query<&Position, (With<Actor>, Without<SelectedToMove>)>,
+    player_query: Query<
+        (Entity, &Position, &StatusEffects),
+        (With<Player>, Without<SelectedToMove>),
+    >,
     map: Res<Map>,
+    turn_count: Res<TurnCount>,
+    mut rng: ResMut<GameRng>,
+    mut attacks: EventWriter<AttackEvent>,
 ) {
     if target_query.is_empty() {
         return;
     }
-    let (entity, mut current_position) = target_query.single_mut();
+    let (entity, mut current_position, status_effects) = target_query.single_mut();
     commands
         .entity(entity)
         .remove::<SelectedToMove>()
         .insert(MovedThisTurn);
 
-    let mut rng = thread_rng();
+    let rng = &mut rng.0;
+    let (player, player_position, player_status_effects) = player_query.single();
 
-    //TODO: Call function to decide delta
-    let delta = PositionDelta::new(rng.gen_range(-1..=1), rng.gen_range(-1..=1));
-    let new_position = *current_position + delta;
+    for _ in 0..status_effects.actions_on_turn(turn_count.0) {
+        //attack the player when next to them, as long as they can be seen
+        if current_position.distance(player_position) == 1
+            && !player_status_effects.has(StatusKind::Invisible)
+        {
+            attacks.send(AttackEvent {
+                attacker: entity,
+                defender: player,
+                kind: AttackKind::Melee,
+            });
+            continue;
+        }
 
-    //check for any collisions with actors
-    if actor_query
-        .iter()
-        .all(|actor_position| *actor_position != new_position)
-    {
-        if let Some(tile) = map.get(new_position.x, new_position.y) {
-            if tile.passable {
-                *current_position = new_position;
+        //TODO: Call function to decide delta
+        let delta = PositionDelta::new(rng.gen_range(-1..=1), rng.gen_range(-1..=1));
+        let delta = status_effects.adjust_movement(delta, rng);
+        let new_position = *current_position + delta;
+
+        //check for any collisions with actors
+        if actor_query
+            .iter()
+            .all(|actor_position| *actor_position != new_position)
+        {
+            if let Some(tile) = map.get(new_position.x, new_position.y) {
+                if tile.passable {
+                    *current_position = new_position;
+                }
             }
         }
     }
@@ -180,72 +316,94 @@ fn clear_moved_markers(mut commands: Commands, query: Query<Entity, With<MovedTh
     });
 }
 
+//Enemies wake once their tile has been explored, unless the player is invisible
 fn update_dormant_enemies(
     mut commands: Commands,
-    query: Query<(Entity, &Position), With<Dormant>>,
+    query: Query<(Entity, &Position, &Name), With<Dormant>>,
+    player_query: Query<&StatusEffects, With<Player>>,
     map: Res<Map>,
+    mut message_log: ResMut<MessageLog>,
 ) {
-    query.iter().for_each(|(entity, position)| {
+    if player_query
+        .get_single()
+        .is_ok_and(|status_effects| status_effects.has(StatusKind::Invisible))
+    {
+        return;
+    }
+    query.iter().for_each(|(entity, position, name)| {
         if let Some(tile) = map.get(position.x, position.y) {
             if tile.view_status != ViewStatus::Unexplored {
                 commands.entity(entity).remove::<Dormant>();
+                if tile.view_status == ViewStatus::Seen {
+                    message_log.push(MessageKind::Warning, format!("The {name} notices you."));
+                }
             }
         }
     })
 }
 
-fn spawn_player(mut commands: Commands, atlas: Res<SpriteAtlas>, map: Res<Map>) {
-    commands.spawn((
-        SpriteSheetBundle {
-            texture_atlas: atlas.handle.clone(),
-            sprite: TextureAtlasSprite::new(SpriteIndex::Player as usize),
-            transform: Transform {
-                translation: Vec3::new(
-                    map.player_spawn_points[0].0 as f32,
-                    map.player_spawn_points[0].1 as f32,
-                    1.0,
-                ) * Vec3::splat(12.0),
-                ..Default::default()
-            },
-            ..Default::default()
+//A player arriving from the level above keeps everything and is only moved
+fn spawn_player(
+    mut commands: Commands,
+    map: Res<Map>,
+    mut player_query: Query<&mut Position, With<Player>>,
+) {
+    let (x, y) = map.player_spawn_points[0];
+    if let Ok(mut position) = player_query.get_single_mut() {
+        *position = Position { x, y };
+        return;
+    }
+    commands.spawn(player_bundle(Position { x, y }));
+}
+
+//Everything a fresh player starts with
+pub fn player_bundle(position: Position) -> impl Bundle {
+    (
+        Renderable {
+            sprite_index: SpriteIndex::Player as usize,
+            layer: 1.0,
         },
-        Actor { _health: 100. },
-        Player,
-        Movement { just_moved: false },
-        Position {
-            x: map.player_spawn_points[0].0,
-            y: map.player_spawn_points[0].1,
+        Actor {
+            health: 100.,
+            max_health: 100.,
+            base_stats: PLAYER_STATS,
         },
-    ));
+        PLAYER_STATS,
+        StatusEffects::default(),
+        Name::new("you"),
+        Player,
+        Inventory::new(20),
+        Equipment::default(),
+        Viewshed::new(15),
+        LightSource::LANTERN,
+        position,
+    )
 }
 
-fn update_transform(mut query: Query<(&mut Transform, &Position), Changed<Position>>) {
-    for (mut trans, pos) in query.iter_mut() {
-        trans.translation.x = pos.x as f32 * 12.0;
-        trans.translation.y = pos.y as f32 * 12.0;
+fn spawn_enemies(mut commands: Commands, map: Res<Map>) {
+    for point in &map.enemy_spawn_points {
+        commands.spawn(bat_bundle(Position::new(point.0, point.1)));
     }
 }
 
-fn spawn_enemies(mut commands: Commands, atlas: Res<SpriteAtlas>, map: Res<Map>) {
-    for point in &map.enemy_spawn_points {
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: atlas.handle.clone(),
-                sprite: TextureAtlasSprite::new(SpriteIndex::Bat as usize),
-                transform: Transform {
-                    translation: Vec3::new(point.0 as f32, point.1 as f32, 1.0) * Vec3::splat(12.0),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Actor { _health: 10. },
-            Enemy,
-            Dormant,
-            Movement { just_moved: false },
-            Position {
-                x: point.0,
-                y: point.1,
-            },
-        ));
-    }
+//A freshly spawned bat, asleep until the player finds it
+pub fn bat_bundle(position: Position) -> impl Bundle {
+    (
+        Renderable {
+            sprite_index: SpriteIndex::Bat as usize,
+            layer: 1.0,
+        },
+        Actor {
+            health: 10.,
+            max_health: 10.,
+            base_stats: BAT_STATS,
+        },
+        BAT_STATS,
+        StatusEffects::default(),
+        Name::new("bat"),
+        Enemy,
+        EnemyKind::Bat,
+        Dormant,
+        position,
+    )
 }
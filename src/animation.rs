@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{Actor, Player},
+    camera_controls::MainCamera,
+    combat::{remove_dead_actors, AttackEvent, AttackKind},
+    fov::InView,
+    game_state::GameplaySet,
+    player_action::{take_player_action, PlayerInput},
+    position::Position,
+    view::{show_invisibility, tint_sprites, update_transforms},
+};
+
+//How long, in seconds, a sprite takes to slide a tile, lunge at what it attacks, flash when hit
+//and show the number floating above it
+const MOVE_TIME: f32 = 0.1;
+const LUNGE_TIME: f32 = 0.15;
+const FLASH_TIME: f32 = 0.15;
+const NUMBER_TIME: f32 = 0.8;
+//How many pixels a lunge reaches towards its target, and a number rises before fading out
+const LUNGE_DISTANCE: f32 = 4.0;
+const NUMBER_RISE: f32 = 12.0;
+const FLASH_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+
+//Slides, lunges, hit flashes and damage numbers. They only ever follow what has already
+//happened, so turns never wait for them, and the next input finishes whatever is playing.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingNumbers>()
+            .add_systems(
+                Update,
+                (
+                    skip_animations.after(take_player_action),
+                    //before the dead are despawned at the end of the frame, so killing blows
+                    //still show
+                    (start_attack_animations, show_health_changes).after(remove_dead_actors),
+                    draw_floating_numbers,
+                )
+                    .chain()
+                    .in_set(GameplaySet),
+            )
+            .add_systems(
+                PostUpdate,
+                animate
+                    .after(update_transforms)
+                    .after(tint_sprites)
+                    .after(show_invisibility)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+//Where a sprite is sliding from and to, and any lunge or hit flash it is partway through
+#[derive(Component)]
+pub struct Animation {
+    from: Vec2,
+    to: Vec2,
+    moved_for: f32,
+    //direction of the attack, and how far into it
+    lunge: Option<(Vec2, f32)>,
+    //colour to go back to, and how far into the flash
+    flash: Option<(Color, f32)>,
+}
+
+impl Animation {
+    pub fn at(translation: Vec2) -> Self {
+        Self {
+            from: translation,
+            to: translation,
+            moved_for: MOVE_TIME,
+            lunge: None,
+            flash: None,
+        }
+    }
+
+    //Slides on from the end of any slide still going, or jumps if it's further than a step away
+    pub fn move_to(&mut self, to: Vec2) {
+        let from = self.to;
+        self.to = to;
+        if from.distance(to) > 12.0 * std::f32::consts::SQRT_2 + 0.1 {
+            self.from = to;
+            self.moved_for = MOVE_TIME;
+        } else {
+            self.from = from;
+            self.moved_for = 0.0;
+        }
+    }
+
+    fn finish(&mut self) {
+        self.moved_for = MOVE_TIME;
+        if let Some((_, elapsed)) = &mut self.lunge {
+            *elapsed = LUNGE_TIME;
+        }
+        if let Some((_, elapsed)) = &mut self.flash {
+            *elapsed = FLASH_TIME;
+        }
+    }
+}
+
+struct FloatingNumber {
+    world_position: Vec2,
+    text: String,
+    color: egui::Color32,
+    age: f32,
+}
+
+#[derive(Resource, Default)]
+struct FloatingNumbers(Vec<FloatingNumber>);
+
+//Acting again finishes everything still playing, rather than making the player wait for it
+fn skip_animations(mut inputs: EventReader<PlayerInput>, mut query: Query<&mut Animation>) {
+    if inputs.iter().count() == 0 {
+        return;
+    }
+    for mut animation in query.iter_mut() {
+        animation.finish();
+    }
+}
+
+fn start_attack_animations(
+    mut attacks: EventReader<AttackEvent>,
+    position_query: Query<&Position>,
+    mut query: Query<(&mut Animation, &TextureAtlasSprite)>,
+) {
+    for attack in attacks.iter() {
+        if let (AttackKind::Melee, Ok(attacker), Ok(defender)) = (
+            attack.kind,
+            position_query.get(attack.attacker),
+            position_query.get(attack.defender),
+        ) {
+            let direction = Vec2::new(
+                defender.x as f32 - attacker.x as f32,
+                defender.y as f32 - attacker.y as f32,
+            )
+            .normalize_or_zero();
+            if let Ok((mut animation, _)) = query.get_mut(attack.attacker) {
+                animation.lunge = Some((direction, 0.0));
+            }
+        }
+        if let Ok((mut animation, sprite)) = query.get_mut(attack.defender) {
+            //a flash already going keeps the colour from before it
+            let base = animation.flash.map_or(sprite.color, |(base, _)| base);
+            animation.flash = Some((base, 0.0));
+        }
+    }
+}
+
+//Floats the damage taken, or health regained, above the player and the enemies in view
+#[allow(clippy::type_complexity)]
+fn show_health_changes(
+    mut last_health: Local<HashMap<Entity, f32>>,
+    query: Query<(
+        Entity,
+        Ref<Actor>,
+        &Position,
+        Option<&Player>,
+        Option<&InView>,
+    )>,
+    mut numbers: ResMut<FloatingNumbers>,
+) {
+    for (entity, actor, position, player, in_view) in query.iter() {
+        let Some(last) = last_health.insert(entity, actor.health) else {
+            continue;
+        };
+        let change = actor.health - last;
+        if !actor.is_changed() || change.abs() < 0.5 || (player.is_none() && in_view.is_none()) {
+            continue;
+        }
+        let (text, color) = if change < 0.0 {
+            (format!("{:.0}", -change), egui::Color32::RED)
+        } else {
+            (format!("+{change:.0}"), egui::Color32::GREEN)
+        };
+        numbers.0.push(FloatingNumber {
+            world_position: Vec2::new(position.x as f32, position.y as f32) * 12.0,
+            text,
+            color,
+            age: 0.0,
+        });
+    }
+    last_health.retain(|entity, _| query.contains(*entity));
+}
+
+fn draw_floating_numbers(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    mut numbers: ResMut<FloatingNumbers>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    for number in numbers.0.iter_mut() {
+        number.age += time.delta_seconds();
+    }
+    numbers.0.retain(|number| number.age < NUMBER_TIME);
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    //behind the windows, as the numbers belong to the map
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("floating_numbers"),
+    ));
+    for number in &numbers.0 {
+        let progress = number.age / NUMBER_TIME;
+        let world_position = number.world_position + Vec2::new(0.0, 6.0 + NUMBER_RISE * progress);
+        let Some(screen_position) =
+            camera.world_to_viewport(camera_transform, world_position.extend(0.0))
+        else {
+            continue;
+        };
+        let alpha = ((1.0 - progress) * 255.0) as u8;
+        let [r, g, b, _] = number.color.to_array();
+        painter.text(
+            egui::pos2(screen_position.x, screen_position.y),
+            egui::Align2::CENTER_BOTTOM,
+            &number.text,
+            egui::FontId::proportional(14.0),
+            egui::Color32::from_rgba_unmultiplied(r, g, b, alpha),
+        );
+    }
+}
+
+//Eases in and out, so slides don't start or stop abruptly
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn animate(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut TextureAtlasSprite, &mut Animation)>,
+) {
+    let delta = time.delta_seconds();
+    for (mut transform, mut sprite, mut animation) in query.iter_mut() {
+        animation.moved_for = (animation.moved_for + delta).min(MOVE_TIME);
+        let mut translation = animation
+            .from
+            .lerp(animation.to, smoothstep(animation.moved_for / MOVE_TIME));
+
+        if let Some((direction, elapsed)) = animation.lunge {
+            let elapsed = elapsed + delta;
+            if elapsed >= LUNGE_TIME {
+                animation.lunge = None;
+            } else {
+                //out towards the target over the first half, back over the second
+                let reach = 1.0 - (2.0 * elapsed / LUNGE_TIME - 1.0).abs();
+                translation += direction * LUNGE_DISTANCE * reach;
+                animation.lunge = Some((direction, elapsed));
+            }
+        }
+        if transform.translation.truncate() != translation {
+            transform.translation = translation.extend(transform.translation.z);
+        }
+
+        if let Some((base, elapsed)) = animation.flash {
+            let elapsed = elapsed + delta;
+            //the alpha is left alone, as invisibility is shown through it
+            let alpha = sprite.color.a();
+            //anything but the flash showing means the sprite was tinted again since, and that
+            //tint is the one to go back to
+            let base = if sprite.color.with_a(1.0) != FLASH_COLOR {
+                sprite.color
+            } else {
+                base
+            };
+            if elapsed >= FLASH_TIME {
+                sprite.color = base.with_a(alpha);
+                animation.flash = None;
+            } else {
+                sprite.color = FLASH_COLOR.with_a(alpha);
+                animation.flash = Some((base, elapsed));
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Enemy, Player, TurnState},
+    fov::{InView, Spotted},
+    game_state::{AppState, GameplaySet},
+    item::{Item, ItemDefinitions},
+    level_generation::map::{Map, ViewStatus},
+    message_log::{MessageKind, MessageLog},
+    pathfinding::{is_frontier, is_known_passable, path_to_nearest, step_towards},
+    player_action::{accepting_actions, take_player_action, PlayerAction, PlayerInput},
+    position::Position,
+};
+
+pub struct AutoExplorePlugin;
+
+impl Plugin for AutoExplorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutoExplore>()
+            .add_systems(OnExit(AppState::Playing), stop_exploring)
+            .add_systems(
+                Update,
+                explore
+                    .before(take_player_action)
+                    .run_if(state_exists_and_equals(TurnState::Player).and_then(accepting_actions))
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Walks the player one step a turn towards the nearest unexplored part of the map, until
+//something worth stopping for turns up
+#[derive(Resource, Default)]
+pub struct AutoExplore {
+    pub active: bool,
+    //floor items already known of when exploring started, which don't stop it again
+    known_items: Option<HashSet<Entity>>,
+}
+
+impl AutoExplore {
+    pub fn start(&mut self) {
+        self.active = true;
+        self.known_items = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.known_items = None;
+    }
+}
+
+fn stop_exploring(mut auto_explore: ResMut<AutoExplore>) {
+    auto_explore.stop();
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn explore(
+    mut auto_explore: ResMut<AutoExplore>,
+    player_query: Query<&Position, With<Player>>,
+    enemy_query: Query<&Name, (With<Enemy>, With<InView>)>,
+    item_query: Query<(Entity, &Item, &Position, Option<&Spotted>)>,
+    map: Res<Map>,
+    item_definitions: Res<ItemDefinitions>,
+    mut message_log: ResMut<MessageLog>,
+    mut inputs: ResMut<Events<PlayerInput>>,
+) {
+    if !auto_explore.active {
+        return;
+    }
+    //anything else the player does this turn takes over from exploring
+    if inputs.iter_current_update_events().next().is_some() {
+        auto_explore.stop();
+        return;
+    }
+    let Ok(position) = player_query.get_single() else {
+        return;
+    };
+
+    let starting = auto_explore.known_items.is_none();
+    let known_items = auto_explore.known_items.get_or_insert_with(|| {
+        item_query
+            .iter()
+            .filter(|(_, _, _, spotted)| spotted.is_some())
+            .map(|(entity, ..)| entity)
+            .collect()
+    });
+    let spotted_item = item_query.iter().find(|(entity, _, position, _)| {
+        !known_items.contains(entity)
+            && map
+                .get(position.x, position.y)
+                .is_some_and(|tile| tile.view_status == ViewStatus::Seen)
+    });
+
+    if let Some(name) = enemy_query.iter().next() {
+        let text = if starting {
+            format!("You can't explore with a {name} in view.")
+        } else {
+            format!("You see a {name} and stop exploring.")
+        };
+        message_log.push(MessageKind::Warning, text);
+        auto_explore.stop();
+    } else if let Some((_, item, ..)) = spotted_item {
+        message_log.push(
+            MessageKind::Info,
+            format!(
+                "You spot {} and stop exploring.",
+                item_definitions[item.kind].describe(item.count)
+            ),
+        );
+        auto_explore.stop();
+    } else if let Some(path) = path_to_nearest(
+        &map,
+        *position,
+        |next| is_known_passable(&map, next),
+        |next| next != *position && is_frontier(&map, next),
+    ) {
+        inputs.send(PlayerInput(PlayerAction::Move(step_towards(
+            *position, path[0],
+        ))));
+    } else {
+        message_log.push(MessageKind::Info, "There is nothing left to explore.");
+        auto_explore.stop();
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Actor, Enemy, Player, TurnState},
+    combat::{remove_dead_actors, resolve_attacks},
+    effect::{Effect, Targeting},
+    fov::{InView, Spotted},
+    game_state::GameplaySet,
+    item::{Equipment, Inventory, InventoryAction, Item, ItemDefinitions},
+    level_generation::map::Map,
+    pathfinding::{is_frontier, is_known_passable, path_to_nearest, step_towards},
+    player_action::{accepting_actions, take_player_action, PlayerAction, PlayerInput},
+    position::Position,
+};
+
+//Below this fraction of its health the bot drinks whatever heals it
+const LOW_HEALTH: f32 = 0.4;
+
+//Plays the game by itself, sending the same inputs the keyboard and the UI do. Used for soak
+//testing, see src/debug/bot.rs.
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BotStats>().add_systems(
+            Update,
+            (
+                play_turn
+                    .before(take_player_action)
+                    .run_if(state_exists_and_equals(TurnState::Player).and_then(accepting_actions)),
+                //between the damage being dealt and the dead being despawned
+                count_kills
+                    .after(resolve_attacks)
+                    .before(remove_dead_actors),
+                count_pickups,
+            )
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BotStats {
+    pub actions: u32,
+    pub kills: u32,
+    pub pickups: u32,
+    //set when the bot found nothing at all to do, such as with the stairs out of reach
+    pub stuck: bool,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn play_turn(
+    player_query: Query<(&Position, &Actor, &Inventory, &Equipment), With<Player>>,
+    item_query: Query<(&Item, Option<&Position>)>,
+    floor_item_query: Query<&Position, (With<Item>, With<Spotted>)>,
+    enemy_query: Query<&Position, (With<Enemy>, With<InView>)>,
+    map: Res<Map>,
+    item_definitions: Res<ItemDefinitions>,
+    mut stats: ResMut<BotStats>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    let Ok((position, actor, inventory, equipment)) = player_query.get_single() else {
+        return;
+    };
+    let carried = || {
+        inventory
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, entity)| Some((slot, entity, item_query.get(*entity).ok()?.0)))
+    };
+
+    let heal = carried().find(|(_, _, item)| {
+        let definition = &item_definitions[item.kind];
+        matches!(definition.targeting, Targeting::User)
+            && definition
+                .effects
+                .iter()
+                .any(|effect| matches!(effect, Effect::Heal(_)))
+    });
+    let equip = carried().find(|(_, _, item)| {
+        item_definitions[item.kind]
+            .equipment
+            .is_some_and(|definition| equipment.slot(definition.slot).is_none())
+    });
+    let floor_items: Vec<Position> = floor_item_query.iter().copied().collect();
+
+    let path_to = |is_goal: &dyn Fn(Position) -> bool| {
+        path_to_nearest(
+            &map,
+            *position,
+            |next| is_known_passable(&map, next),
+            |next| next != *position && is_goal(next),
+        )
+        .map(|path| PlayerAction::Move(step_towards(*position, path[0])))
+    };
+    let on_stairs = map
+        .get(position.x, position.y)
+        .is_some_and(|tile| tile.stairs);
+
+    let action = if !inventory.is_full() && floor_items.contains(position) {
+        Some(PlayerAction::PickUp)
+    } else if let Some((slot, _, _)) = heal.filter(|_| actor.health < actor.max_health * LOW_HEALTH)
+    {
+        Some(PlayerAction::Inventory(InventoryAction::Use(slot, None)))
+    } else if let Some((slot, _, _)) = equip {
+        Some(PlayerAction::Inventory(InventoryAction::Equip(slot)))
+    } else if let Some(enemy) = enemy_query
+        .iter()
+        .find(|enemy| enemy.distance(position) == 1)
+    {
+        Some(PlayerAction::Move(step_towards(*position, *enemy)))
+    } else if let Some(action) = (!inventory.is_full())
+        .then(|| path_to(&|tile| floor_items.contains(&tile)))
+        .flatten()
+    {
+        Some(action)
+    } else if let Some(action) = path_to(&|tile| is_frontier(&map, tile)) {
+        Some(action)
+    } else if on_stairs {
+        Some(PlayerAction::Descend)
+    } else {
+        path_to(&|tile| map.get(tile.x, tile.y).is_some_and(|tile| tile.stairs))
+    };
+
+    match action {
+        Some(action) => {
+            stats.actions += 1;
+            inputs.send(PlayerInput(action));
+        }
+        None => stats.stuck = true,
+    }
+}
+
+fn count_kills(query: Query<&Actor, (With<Enemy>, Changed<Actor>)>, mut stats: ResMut<BotStats>) {
+    stats.kills += query.iter().filter(|actor| actor.health <= 0.).count() as u32;
+}
+
+//Anything added to what the player carries was picked up, whether it took a new slot or topped
+//up a stack
+fn count_pickups(
+    player_query: Query<&Inventory, With<Player>>,
+    item_query: Query<&Item>,
+    mut last_carried: Local<Option<u32>>,
+    mut stats: ResMut<BotStats>,
+) {
+    let Ok(inventory) = player_query.get_single() else {
+        return;
+    };
+    let carried = inventory
+        .items
+        .iter()
+        .filter_map(|entity| item_query.get(*entity).ok())
+        .map(|item| item.count)
+        .sum();
+    if last_carried.is_some_and(|last_carried| carried > last_carried) {
+        stats.pickups += 1;
+    }
+    *last_carried = Some(carried);
+}
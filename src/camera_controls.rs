@@ -1,8 +1,20 @@
 use bevy::{
-    input::mouse::{MouseMotion, MouseWheel},
+    input::{
+        mouse::{MouseMotion, MouseWheel},
+        touch::Touch,
+    },
     prelude::*,
+    transform::TransformSystem,
     window::PrimaryWindow,
 };
+use bevy_egui::EguiContexts;
+
+use crate::{gamepad_controls::GamepadInput, level_generation::map::Map};
+
+//How far the right stick has to be pushed before it pans the camera, and how fast it pans at
+//full tilt, in pixels a second
+const PAN_DEADZONE: f32 = 0.2;
+const PAN_SPEED: f32 = 400.0;
 
 #[derive(Component)]
 pub struct MainCamera;
@@ -11,18 +23,111 @@ pub struct CameraControlsPlugin;
 
 impl Plugin for CameraControlsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (mouse_drag, mouse_zoom));
+        app.init_resource::<CameraMode>()
+            .init_resource::<CameraSettings>()
+            .add_systems(
+                Update,
+                (mouse_drag, mouse_zoom, gamepad_pan, touch_pan_zoom),
+            )
+            .add_systems(
+                PostUpdate,
+                (limit_zoom, clamp_camera.run_if(resource_exists::<Map>()))
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+//Whether the camera is following the player, or has been moved away by hand to look around
+#[derive(Resource, Default, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CameraMode {
+    #[default]
+    Follow,
+    //until the player next moves or the camera is recentred
+    FreeLook,
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CameraSettings {
+    //how quickly the camera catches up with the player, higher is snappier
+    pub follow_speed: f32,
+    //how far the camera can zoom out and in, as screen pixels per texel
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    //keeps the zoom to whole numbers of screen pixels per texel, or of texels per screen pixel,
+    //so sprites stay crisp
+    pub pixel_snapping: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            follow_speed: 10.0,
+            min_zoom: 0.25,
+            max_zoom: 4.0,
+            pixel_snapping: true,
+        }
+    }
+}
+
+impl CameraSettings {
+    //Keeps a projection scale within the zoom limits, snapped if pixel snapping is on
+    pub fn limit_scale(&self, scale: f32) -> f32 {
+        let (min_scale, max_scale) = (1.0 / self.max_zoom, 1.0 / self.min_zoom);
+        let scale = scale.clamp(min_scale, max_scale);
+        if !self.pixel_snapping {
+            return scale;
+        }
+        let snapped = level_scale(zoom_level(scale));
+        if (min_scale..=max_scale).contains(&snapped) {
+            snapped
+        } else {
+            scale
+        }
     }
 }
 
+//Crisp zoom levels counted from 1:1, up for zooming in and down for zooming out
+fn zoom_level(scale: f32) -> i32 {
+    if scale <= 1.0 {
+        (1.0 / scale).round() as i32 - 1
+    } else {
+        1 - scale.round() as i32
+    }
+}
+
+fn level_scale(level: i32) -> f32 {
+    if level >= 0 {
+        1.0 / (level + 1) as f32
+    } else {
+        (1 - level) as f32
+    }
+}
+
+//Centres the camera on a point in the world and leaves it there, as clicking the minimap does
+pub fn pan_camera_to(target: Vec2, camera_transform: &mut Transform, mode: &mut CameraMode) {
+    camera_transform.translation.x = target.x;
+    camera_transform.translation.y = target.y;
+    *mode = CameraMode::FreeLook;
+}
+
+//Whether the mouse is over, or being used by, an egui window such as the minimap
+fn pointer_over_ui(contexts: &mut EguiContexts) -> bool {
+    let ctx = contexts.ctx_mut();
+    ctx.is_pointer_over_area() || ctx.is_using_pointer()
+}
+
 fn mouse_drag(
+    mut contexts: EguiContexts,
+    mut mode: ResMut<CameraMode>,
     mouse: Res<Input<MouseButton>>,
     mut motion_evr: EventReader<MouseMotion>,
     mut camera_query: Query<&mut Transform, With<MainCamera>>,
     projection_query: Query<&OrthographicProjection, With<MainCamera>>,
     window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
-    if window_query.single().cursor_position().is_none() {
+    if window_query.single().cursor_position().is_none() || pointer_over_ui(&mut contexts) {
+        motion_evr.clear();
         return;
     }
     if mouse.pressed(MouseButton::Left) {
@@ -31,21 +136,158 @@ fn mouse_drag(
         for event in motion_evr.iter() {
             camera_transform.translation.x -= event.delta.x * zoom_level;
             camera_transform.translation.y += event.delta.y * zoom_level;
+            *mode = CameraMode::FreeLook;
         }
     }
 }
 
 fn mouse_zoom(
+    mut contexts: EguiContexts,
+    settings: Res<CameraSettings>,
     mut scroll_evr: EventReader<MouseWheel>,
     mut projection_query: Query<&mut OrthographicProjection, With<MainCamera>>,
 ) {
+    if pointer_over_ui(&mut contexts) {
+        scroll_evr.clear();
+        return;
+    }
     let mut projection = projection_query.single_mut();
     for event in scroll_evr.iter() {
+        //snapped zoom steps a whole level at a time, as anything in between would be undone
+        if settings.pixel_snapping {
+            let level_delta = match event.y {
+                y if y > 0.0 => 1,
+                y if y < 0.0 => -1,
+                _ => 0,
+            };
+            let level = zoom_level(projection.scale) + level_delta;
+            projection.scale = settings.limit_scale(level_scale(level));
+            continue;
+        }
         let zoom_delta = match event.y {
             y if y > 0.0 => 1.0 / 1.1,
             y if y < 0.0 => 1.1,
             _ => 1.0,
         };
-        projection.scale *= zoom_delta;
+        projection.scale = settings.limit_scale(projection.scale * zoom_delta);
+    }
+}
+
+fn gamepad_pan(
+    gamepad: GamepadInput,
+    mut mode: ResMut<CameraMode>,
+    time: Res<Time>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    let stick = gamepad.right_stick();
+    if stick.length() < PAN_DEADZONE {
+        return;
+    }
+    let Ok((mut camera_transform, projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+    camera_transform.translation +=
+        (stick * PAN_SPEED * projection.scale * time.delta_seconds()).extend(0.0);
+    *mode = CameraMode::FreeLook;
+}
+
+//Two fingers pan the camera as they move together and zoom it as they pinch
+fn touch_pan_zoom(
+    touches: Res<Touches>,
+    mut mode: ResMut<CameraMode>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let fingers: Vec<&Touch> = touches.iter().collect();
+    let [first, second] = fingers.as_slice() else {
+        return;
+    };
+    let (Ok((mut camera_transform, mut projection)), Ok(window)) =
+        (camera_query.get_single_mut(), window_query.get_single())
+    else {
+        return;
+    };
+    //touches come in physical pixels
+    let scale_factor = window.scale_factor() as f32;
+
+    let midpoint = (first.position() + second.position()) / 2.0;
+    let previous_midpoint = (first.previous_position() + second.previous_position()) / 2.0;
+    let pan = (midpoint - previous_midpoint) / scale_factor * projection.scale;
+    if pan != Vec2::ZERO {
+        camera_transform.translation.x -= pan.x;
+        camera_transform.translation.y += pan.y;
+        *mode = CameraMode::FreeLook;
+    }
+
+    let spread = first.position().distance(second.position());
+    let previous_spread = first
+        .previous_position()
+        .distance(second.previous_position());
+    if spread > 0.0 && previous_spread > 0.0 {
+        projection.scale *= previous_spread / spread;
+    }
+}
+
+//Holds the zoom within the limits. A pinch is snapped to a crisp level once the fingers lift.
+fn limit_zoom(
+    settings: Res<CameraSettings>,
+    touches: Res<Touches>,
+    mut projection_query: Query<&mut OrthographicProjection, With<MainCamera>>,
+) {
+    let Ok(mut projection) = projection_query.get_single_mut() else {
+        return;
+    };
+    let scale = if touches.iter().count() > 1 {
+        projection
+            .scale
+            .clamp(1.0 / settings.max_zoom, 1.0 / settings.min_zoom)
+    } else {
+        settings.limit_scale(projection.scale)
+    };
+    //only written when it differs, so the projection isn't marked changed every frame
+    if projection.scale != scale {
+        projection.scale = scale;
     }
 }
+
+//Keeps the view over the map, or centred on it where the map is smaller than the view
+pub fn clamp_camera(
+    map: Res<Map>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    let (Ok(window), Ok((mut camera_transform, projection))) =
+        (window_query.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+    let half_view = Vec2::new(window.width(), window.height()) / 2.0 * projection.scale;
+    //tiles are 12 pixels, centred on their position
+    let map_min = Vec2::splat(-6.0);
+    let map_max = Vec2::new(map.width as f32, map.height as f32) * 12.0 - 6.0;
+
+    let clamp_axis = |value: f32, min: f32, max: f32, half: f32| {
+        if max - min <= half * 2.0 {
+            (min + max) / 2.0
+        } else {
+            value.clamp(min + half, max - half)
+        }
+    };
+    let translation = camera_transform.translation;
+    let x = clamp_axis(translation.x, map_min.x, map_max.x, half_view.x);
+    let y = clamp_axis(translation.y, map_min.y, map_max.y, half_view.y);
+    if x != translation.x || y != translation.y {
+        camera_transform.translation.x = x;
+        camera_transform.translation.y = y;
+    }
+}
+
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+}
@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Actor, CombatStats, Player, TurnState},
+    game_state::GameplaySet,
+    item::{Equipment, Inventory, Item, ItemDefinitions},
+    level_generation::map::Map,
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction},
+    position::Position,
+};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AttackEvent>().add_systems(
+            Update,
+            (
+                fire_ranged_weapon
+                    .after(take_player_action)
+                    .run_if(state_exists_and_equals(TurnState::Player)),
+                (resolve_attacks, remove_dead_actors).chain(),
+            )
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum AttackKind {
+    Melee,
+    //ranged and magic damage comes from the weapon or spell rather than the attacker's stats
+    Ranged(f32),
+    Magic(f32),
+}
+
+impl AttackKind {
+    fn verb(&self) -> &'static str {
+        match self {
+            AttackKind::Melee => "hit",
+            AttackKind::Ranged(_) => "shoot",
+            AttackKind::Magic(_) => "blast",
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub defender: Entity,
+    pub kind: AttackKind,
+}
+
+#[derive(Component)]
+pub struct Dead;
+
+//Follows the line from start towards target, stopping in front of the first blocking tile
+//or on the first actor in the way. Returns where the projectile landed and who it hit.
+pub fn trace_projectile(
+    map: &Map,
+    start: Position,
+    target: Position,
+    actors: &[(Entity, Position)],
+) -> (Position, Option<Entity>) {
+    let mut impact = start;
+    for (x, y) in Map::line((start.x, start.y), (target.x, target.y)) {
+        if !map.get(x, y).is_some_and(|tile| tile.passable) {
+            break;
+        }
+        impact = Position::new(x, y);
+        if let Some((entity, _)) = actors.iter().find(|(_, position)| *position == impact) {
+            return (impact, Some(*entity));
+        }
+    }
+    (impact, None)
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn fire_ranged_weapon(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerAction>,
+    mut attacks: EventWriter<AttackEvent>,
+    mut player_query: Query<(Entity, &Position, &mut Inventory, &Equipment), With<Player>>,
+    actor_query: Query<(Entity, &Position), (With<Actor>, Without<Player>)>,
+    mut item_query: Query<&mut Item>,
+    item_definitions: Res<ItemDefinitions>,
+    map: Res<Map>,
+    mut message_log: ResMut<MessageLog>,
+    mut next_state: ResMut<NextState<TurnState>>,
+) {
+    let Some(target) = actions.iter().find_map(|action| match action {
+        PlayerAction::Fire(target) => Some(*target),
+        _ => None,
+    }) else {
+        return;
+    };
+
+    let (player, player_position, mut inventory, equipment) = player_query.single_mut();
+    let Some(ranged) = equipment
+        .weapon
+        .and_then(|weapon| item_query.get(weapon).ok())
+        .and_then(|weapon| item_definitions[weapon.kind].ranged.clone())
+    else {
+        message_log.push(MessageKind::Warning, "You have nothing to fire.");
+        return;
+    };
+    if target.distance(player_position) > ranged.range {
+        message_log.push(MessageKind::Warning, "That is out of range.");
+        return;
+    }
+
+    if let Some(ammo) = &ranged.ammo {
+        let Some(ammo_entity) = inventory.items.iter().copied().find(|entity| {
+            item_query
+                .get(*entity)
+                .is_ok_and(|item| item_definitions[item.kind].name == *ammo)
+        }) else {
+            message_log.push(MessageKind::Warning, format!("You have no {ammo}s left."));
+            return;
+        };
+        let mut ammo_item = item_query.get_mut(ammo_entity).unwrap();
+        ammo_item.count -= 1;
+        if ammo_item.count == 0 {
+            inventory.remove(ammo_entity);
+            commands.entity(ammo_entity).despawn_recursive();
+        }
+    }
+
+    let actors: Vec<(Entity, Position)> = actor_query
+        .iter()
+        .map(|(entity, position)| (entity, *position))
+        .collect();
+    match trace_projectile(&map, *player_position, target, &actors) {
+        (_, Some(defender)) => attacks.send(AttackEvent {
+            attacker: player,
+            defender,
+            kind: AttackKind::Ranged(ranged.damage),
+        }),
+        (_, None) => message_log.push(MessageKind::Info, "Your shot misses."),
+    }
+
+    next_state.set(TurnState::Enemy);
+}
+
+pub fn resolve_attacks(
+    mut events: EventReader<AttackEvent>,
+    mut actor_query: Query<(&mut Actor, &CombatStats, &Name, Option<&Player>)>,
+    mut message_log: ResMut<MessageLog>,
+) {
+    for event in events.iter() {
+        let Ok((_, attacker_stats, attacker_name, attacker_player)) =
+            actor_query.get(event.attacker)
+        else {
+            continue;
+        };
+        let (attack, attacker_name, attacker_is_player) = (
+            attacker_stats.attack,
+            attacker_name.to_string(),
+            attacker_player.is_some(),
+        );
+        let Ok((mut defender, defender_stats, defender_name, defender_player)) =
+            actor_query.get_mut(event.defender)
+        else {
+            continue;
+        };
+
+        let damage = match event.kind {
+            AttackKind::Melee => attack,
+            AttackKind::Ranged(damage) | AttackKind::Magic(damage) => damage,
+        };
+        let damage = (damage - defender_stats.defense).max(1.);
+        defender.health -= damage;
+
+        let verb = event.kind.verb();
+        if attacker_is_player {
+            message_log.push(
+                MessageKind::Info,
+                format!("You {verb} the {defender_name} for {damage:.0}."),
+            );
+        } else if defender_player.is_some() {
+            message_log.push(
+                MessageKind::Danger,
+                format!("The {attacker_name} {verb}s you for {damage:.0}."),
+            );
+        } else {
+            message_log.push(
+                MessageKind::Info,
+                format!("The {attacker_name} {verb}s the {defender_name}."),
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn remove_dead_actors(
+    mut commands: Commands,
+    query: Query<(Entity, &Actor, &Name, Option<&Player>), (Changed<Actor>, Without<Dead>)>,
+    mut message_log: ResMut<MessageLog>,
+) {
+    for (entity, actor, name, player) in query.iter() {
+        if actor.health > 0. {
+            continue;
+        }
+        if player.is_some() {
+            message_log.push(MessageKind::Danger, "You die...");
+            commands.entity(entity).insert(Dead);
+        } else {
+            message_log.push(MessageKind::Good, format!("The {name} dies."));
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//Plays whole runs headless with the bot, one per seed, and reports how each of them ended.
+//Usage: bot [runs] [first seed] [turn limit], best built with --release for large batches.
+//A run that went wrong is played again the same way by passing its seed with a single run.
+use std::{
+    panic::{self, AssertUnwindSafe},
+    process::ExitCode,
+    sync::Mutex,
+    time::Instant,
+};
+
+use bevy::prelude::*;
+use roguelike::{
+    actor::{Actor, Player, TurnCount},
+    bot::{BotPlugin, BotStats},
+    game_state::{AppState, NextRunSeed},
+    level_generation::Depth,
+    replay::ReplayPlugin,
+    SimulationPlugins,
+};
+
+//Frames in a row without a turn going by before the run counts as stuck
+const STUCK_FRAMES: u32 = 1000;
+
+//Message of the last panic, filled in by the panic hook
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum Outcome {
+    Victory,
+    Died,
+    TurnLimit,
+    Stuck,
+    Crashed,
+}
+
+impl Outcome {
+    fn is_failure(&self) -> bool {
+        matches!(self, Outcome::TurnLimit | Outcome::Stuck | Outcome::Crashed)
+    }
+}
+
+struct RunReport {
+    outcome: Outcome,
+    //why a run got stuck or crashed
+    detail: Option<String>,
+    depth: u32,
+    turns: u32,
+    health: f32,
+    actions: u32,
+    kills: u32,
+    pickups: u32,
+}
+
+impl RunReport {
+    fn crashed() -> Self {
+        Self {
+            outcome: Outcome::Crashed,
+            detail: LAST_PANIC.lock().unwrap().take(),
+            depth: 0,
+            turns: 0,
+            health: 0.,
+            actions: 0,
+            kills: 0,
+            pickups: 0,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<u64> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("arguments should be whole numbers"))
+        .collect();
+    let runs = args.first().copied().unwrap_or(100);
+    let first_seed = args.get(1).copied().unwrap_or(0);
+    let turn_limit = args.get(2).copied().unwrap_or(20_000) as u32;
+
+    panic::set_hook(Box::new(|info| {
+        *LAST_PANIC.lock().unwrap() = Some(info.to_string());
+    }));
+
+    let mut reports = Vec::new();
+    for seed in first_seed..first_seed + runs {
+        let started = Instant::now();
+        let report = panic::catch_unwind(AssertUnwindSafe(|| play(seed, turn_limit)))
+            .unwrap_or_else(|_| RunReport::crashed());
+        println!(
+            "seed {seed:>8} {:>9} depth {} turns {:>6} hp {:>5.1} actions {:>6} kills {:>4} pickups {:>4} {:>6}ms{}",
+            format!("{:?}", report.outcome),
+            report.depth,
+            report.turns,
+            report.health,
+            report.actions,
+            report.kills,
+            report.pickups,
+            started.elapsed().as_millis(),
+            report
+                .detail
+                .as_ref()
+                .map_or(String::new(), |detail| format!(" ({detail})")),
+        );
+        reports.push(report);
+    }
+
+    println!();
+    let mut outcomes: Vec<Outcome> = reports.iter().map(|report| report.outcome).collect();
+    outcomes.sort();
+    outcomes.dedup();
+    for outcome in outcomes {
+        let count = reports
+            .iter()
+            .filter(|report| report.outcome == outcome)
+            .count();
+        println!("{outcome:?}: {count}");
+    }
+    if !reports.is_empty() {
+        let total_turns: u32 = reports.iter().map(|report| report.turns).sum();
+        let deepest = reports.iter().map(|report| report.depth).max().unwrap();
+        println!(
+            "average turns {}, deepest level {deepest}",
+            total_turns / reports.len() as u32
+        );
+    }
+
+    if reports.iter().any(|report| report.outcome.is_failure()) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn play(seed: u64, turn_limit: u32) -> RunReport {
+    let mut app = App::new();
+    //the bot's runs would otherwise overwrite the player's replay
+    app.add_plugins((
+        MinimalPlugins,
+        SimulationPlugins.build().disable::<ReplayPlugin>(),
+        BotPlugin,
+    ))
+    .insert_resource(NextRunSeed(seed));
+    app.update();
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::NewGame);
+
+    let mut last_turn = 0;
+    let mut idle_frames = 0;
+    let (outcome, detail) = loop {
+        app.update();
+
+        let state = *app.world.resource::<State<AppState>>().get();
+        let turn = app
+            .world
+            .get_resource::<TurnCount>()
+            .map_or(0, |turns| turns.0);
+        if turn == last_turn {
+            idle_frames += 1;
+        } else {
+            last_turn = turn;
+            idle_frames = 0;
+        }
+
+        if state == AppState::Victory {
+            break (Outcome::Victory, None);
+        } else if state == AppState::GameOver {
+            break (Outcome::Died, None);
+        } else if app.world.resource::<BotStats>().stuck {
+            break (Outcome::Stuck, Some("nothing left to do".to_string()));
+        } else if idle_frames > STUCK_FRAMES {
+            break (
+                Outcome::Stuck,
+                Some(format!("no turn taken in {STUCK_FRAMES} frames")),
+            );
+        } else if turn > turn_limit {
+            break (Outcome::TurnLimit, None);
+        }
+    };
+
+    let health = app
+        .world
+        .query_filtered::<&Actor, With<Player>>()
+        .get_single(&app.world)
+        .map_or(0., |actor| actor.health);
+    let stats = app.world.resource::<BotStats>();
+    RunReport {
+        outcome,
+        detail,
+        depth: app.world.get_resource::<Depth>().map_or(0, |depth| depth.0),
+        turns: last_turn,
+        health,
+        actions: stats.actions,
+        kills: stats.kills,
+        pickups: stats.pickups,
+    }
+}
@@ -6,7 +6,8 @@ use bevy_egui::{
 use roguelike::level_generation::map::Map;
 use roguelike::{
     camera_controls::{CameraControlsPlugin, MainCamera},
-    level_generation::{generators::MapGeneratorSettings, MapPlugin},
+    level_generation::generators::MapGeneratorSettings,
+    map_view::{MapRenderSettings, MapViewPlugin},
     sprite_atlas::SpriteAtlasPlugin,
 };
 
@@ -16,7 +17,7 @@ fn main() {
         .add_plugins((
             EguiPlugin,
             CameraControlsPlugin,
-            MapPlugin,
+            MapViewPlugin,
             SpriteAtlasPlugin,
         ))
         .insert_resource(ClearColor(Color::BLACK))
@@ -32,9 +33,10 @@ fn setup(mut commands: Commands) {
     commands.spawn((Camera2dBundle::default(), MainCamera));
 
     let map_settings = MapGeneratorSettings::default();
-    let map = Map::new(map_settings);
+    let map = Map::new(map_settings, &mut rand::thread_rng());
     commands.insert_resource(map_settings);
     commands.insert_resource(map);
+    commands.insert_resource(MapRenderSettings { fog_of_war: false });
 
     // Spawn second window
     let tools_window = commands
@@ -83,7 +85,7 @@ fn generation_options_ui(
             }
             if ui.button("Reset").clicked() {
                 map.reset();
-                map.generate(*settings);
+                map.generate(*settings, &mut rand::thread_rng());
             }
         },
     );
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    actor::Actor,
+    combat::{trace_projectile, AttackEvent, AttackKind},
+    fov::{calculate_fov, FovChanges},
+    game_state::GameplaySet,
+    level_generation::map::{Map, ViewStatus},
+    lighting::{fire_bundle, Fire},
+    position::Position,
+    rng::GameRng,
+    status::{StatusEffects, StatusKind},
+};
+
+type ActorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Actor,
+        &'static mut Position,
+        &'static mut StatusEffects,
+    ),
+>;
+
+pub struct EffectPlugin;
+
+impl Plugin for EffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EffectEvent>()
+            //after the field of view, which starts each frame by clearing the tiles it changed
+            .add_systems(
+                Update,
+                apply_effects.after(calculate_fov).in_set(GameplaySet),
+            );
+    }
+}
+
+//Building blocks for item behaviour, composed in assets/items.ron
+#[derive(Deserialize, Clone, Debug)]
+pub enum Effect {
+    Heal(f32),
+    //moves the source to a random free tile
+    Teleport,
+    //moves the source to the targeted tile
+    Blink,
+    //reveals the layout of the whole map
+    RevealMap,
+    //affects the actor on the targeted tile, or the source if the item isn't targeted
+    ApplyStatus(StatusKind, u32),
+    //damages the first actor between the source and the targeted tile
+    Projectile(f32),
+    //sets the targeted tile, or the source's if the item isn't targeted, burning for some turns
+    Ignite(u32),
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+pub enum Targeting {
+    #[default]
+    User,
+    //the player picks a visible tile within range
+    Tile {
+        range: usize,
+    },
+}
+
+#[derive(Event)]
+pub struct EffectEvent {
+    pub effects: Vec<Effect>,
+    pub source: Entity,
+    pub target: Option<Position>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_effects(
+    mut commands: Commands,
+    mut events: EventReader<EffectEvent>,
+    mut attacks: EventWriter<AttackEvent>,
+    mut map: ResMut<Map>,
+    mut fov_changes: ResMut<FovChanges>,
+    mut actor_query: ActorQuery,
+    mut fire_query: Query<(&mut Fire, &Position), Without<Actor>>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = &mut rng.0;
+
+    for event in events.iter() {
+        for effect in &event.effects {
+            match effect {
+                Effect::Heal(amount) => {
+                    if let Ok((_, mut actor, _, _)) = actor_query.get_mut(event.source) {
+                        actor.health = (actor.health + amount).min(actor.max_health);
+                    }
+                }
+                Effect::Teleport => {
+                    let destination = (0..1000)
+                        .map(|_| {
+                            Position::new(rng.gen_range(0..map.width), rng.gen_range(0..map.height))
+                        })
+                        .find(|position| is_free(&map, &actor_query, position));
+                    if let Some(destination) = destination {
+                        move_actor(&mut actor_query, event.source, destination);
+                    }
+                }
+                Effect::Blink => {
+                    if let Some(target) = event.target {
+                        if is_free(&map, &actor_query, &target) {
+                            move_actor(&mut actor_query, event.source, target);
+                        }
+                    }
+                }
+                Effect::Projectile(damage) => {
+                    let (Some(target), Ok((_, _, source_position, _))) =
+                        (event.target, actor_query.get(event.source))
+                    else {
+                        continue;
+                    };
+                    let actors: Vec<(Entity, Position)> = actor_query
+                        .iter()
+                        .filter(|(entity, _, _, _)| *entity != event.source)
+                        .map(|(entity, _, position, _)| (entity, *position))
+                        .collect();
+                    if let (_, Some(defender)) =
+                        trace_projectile(&map, *source_position, target, &actors)
+                    {
+                        attacks.send(AttackEvent {
+                            attacker: event.source,
+                            defender,
+                            kind: AttackKind::Magic(*damage),
+                        });
+                    }
+                }
+                Effect::ApplyStatus(kind, turns) => {
+                    let recipient = match event.target {
+                        Some(target) => actor_query
+                            .iter()
+                            .find(|(_, _, position, _)| **position == target)
+                            .map(|(entity, _, _, _)| entity),
+                        None => Some(event.source),
+                    };
+                    if let Some(Ok((_, _, _, mut status_effects))) =
+                        recipient.map(|entity| actor_query.get_mut(entity))
+                    {
+                        status_effects.apply(*kind, *turns);
+                    }
+                }
+                Effect::Ignite(turns) => {
+                    let tile = match event.target {
+                        Some(target) => Some(target),
+                        None => actor_query
+                            .get(event.source)
+                            .ok()
+                            .map(|(_, _, position, _)| *position),
+                    };
+                    let Some(tile) = tile
+                        .filter(|tile| map.get(tile.x, tile.y).is_some_and(|tile| tile.passable))
+                    else {
+                        continue;
+                    };
+                    //relighting a fire only makes it last longer
+                    match fire_query
+                        .iter_mut()
+                        .find(|(_, position)| **position == tile)
+                    {
+                        Some((mut fire, _)) => fire.turns_left = fire.turns_left.max(*turns),
+                        None => {
+                            commands.spawn(fire_bundle(tile, *turns));
+                        }
+                    }
+                }
+                Effect::RevealMap => {
+                    for x in 0..map.width {
+                        for y in 0..map.height {
+                            let tile = map.get_mut(x, y).unwrap();
+                            if tile.view_status == ViewStatus::Unexplored {
+                                tile.view_status = ViewStatus::Revealed;
+                                //so the items and ghosts on them are recoloured
+                                fov_changes.tiles.insert((x, y));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_free(map: &Map, actor_query: &ActorQuery, position: &Position) -> bool {
+    map.get(position.x, position.y)
+        .is_some_and(|tile| tile.passable)
+        && actor_query
+            .iter()
+            .all(|(_, _, actor_position, _)| actor_position != position)
+}
+
+fn move_actor(actor_query: &mut ActorQuery, entity: Entity, destination: Position) {
+    if let Ok((_, _, mut position, _)) = actor_query.get_mut(entity) {
+        *position = destination;
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashSet;
+
 use crate::{
     actor::{Enemy, Player},
-    level_generation::{
-        map::{Map, ViewStatus},
-        MapTile,
-    },
+    game_state::GameplaySet,
+    item::Item,
+    level_generation::map::{Map, ViewStatus},
+    lighting::{update_light_map, LightMap},
+    position::Position,
+    sprite_atlas::Renderable,
 };
 use bevy::prelude::*;
 
@@ -11,157 +15,498 @@ pub struct FovPlugin;
 
 impl Plugin for FovPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (reset_fov, calculate_fov, update_fov).chain());
+        app.init_resource::<FovAlgorithm>()
+            .init_resource::<FovChanges>()
+            .add_systems(
+                Update,
+                (calculate_fov, remember_enemies, spot_items)
+                    .chain()
+                    .after(update_light_map)
+                    .in_set(GameplaySet),
+            );
     }
 }
 
-fn reset_fov(mut map: ResMut<Map>) {
-    for x in 0..map.width {
-        for y in 0..map.height {
-            let tile = map.get_mut(x, y).unwrap();
-            tile.view_status = match tile.view_status {
-                ViewStatus::Revealed => ViewStatus::Revealed,
-                ViewStatus::Seen => ViewStatus::Revealed,
-                ViewStatus::Unexplored => ViewStatus::Unexplored,
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FovAlgorithm {
+    //Albert Ford's variant: floor tiles see each other symmetrically and walls are lit cleanly
+    #[default]
+    SymmetricShadowcasting,
+    //A tile is visible if any line between sample points in the two tiles is unobstructed.
+    //Symmetric and sees round pillars, but much slower than shadowcasting.
+    Permissive,
+    //Bresenham rays to the edge of the radius. Cheap, but not symmetric and leaves artefacts.
+    Raycasting,
+}
+
+impl FovAlgorithm {
+    pub const ALL: [FovAlgorithm; 3] = [
+        FovAlgorithm::SymmetricShadowcasting,
+        FovAlgorithm::Permissive,
+        FovAlgorithm::Raycasting,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FovAlgorithm::SymmetricShadowcasting => "Symmetric shadowcasting",
+            FovAlgorithm::Permissive => "Permissive",
+            FovAlgorithm::Raycasting => "Raycasting",
+        }
+    }
+}
+
+//The tiles an entity can currently see. Only recalculated when the entity moves, its radius
+//changes or the map layout changes.
+#[derive(Component)]
+pub struct Viewshed {
+    pub radius: usize,
+    pub visible: HashSet<(usize, usize)>,
+    //layout version of the map the visible tiles were calculated against
+    layout_version: Option<u64>,
+}
+
+impl Viewshed {
+    pub fn new(radius: usize) -> Self {
+        Self {
+            radius,
+            visible: HashSet::new(),
+            layout_version: None,
+        }
+    }
+}
+
+//Faded marker left where the player last saw an enemy that has since moved out of view
+#[derive(Component)]
+pub struct Ghost {
+    pub enemy: Entity,
+}
+
+//Where the enemy was when the player last saw it
+#[derive(Component)]
+pub struct LastSeen(pub Position);
+
+//On enemies the player can currently see
+#[derive(Component)]
+pub struct InView;
+
+//On floor items the player has seen, so items on tiles that were only revealed stay unknown
+#[derive(Component)]
+pub struct Spotted;
+
+//Tiles that entered or left the player's view this frame, so sprites on them can be recoloured
+#[derive(Resource, Default)]
+pub struct FovChanges {
+    pub tiles: HashSet<(usize, usize)>,
+}
+
+//Enemies are only known about while their tile is in view. When one drops out of view a ghost is
+//left at its last known position, until that tile is seen again or the enemy turns up elsewhere.
+#[allow(clippy::type_complexity)]
+pub fn remember_enemies(
+    mut commands: Commands,
+    map: Res<Map>,
+    mut enemy_query: Query<
+        (
+            Entity,
+            &Position,
+            &Name,
+            &Renderable,
+            Option<&InView>,
+            Option<&mut LastSeen>,
+        ),
+        With<Enemy>,
+    >,
+    ghost_query: Query<(Entity, &Ghost, &Position)>,
+) {
+    let is_seen = |position: &Position| {
+        map.get(position.x, position.y)
+            .is_some_and(|tile| tile.view_status == ViewStatus::Seen)
+    };
+
+    for (ghost, _, position) in ghost_query.iter() {
+        if is_seen(position) {
+            commands.entity(ghost).despawn();
+        }
+    }
+
+    for (enemy, position, name, renderable, in_view, last_seen) in enemy_query.iter_mut() {
+        if is_seen(position) {
+            if in_view.is_none() {
+                commands.entity(enemy).insert(InView);
+            }
+            match last_seen {
+                Some(mut last_seen) => last_seen.0 = *position,
+                None => {
+                    commands.entity(enemy).insert(LastSeen(*position));
+                }
+            }
+            //ghosts on seen tiles were already removed above
+            for (ghost, marker, ghost_position) in ghost_query.iter() {
+                if marker.enemy == enemy && !is_seen(ghost_position) {
+                    commands.entity(ghost).despawn();
+                }
             }
+        } else if in_view.is_some() {
+            commands.entity(enemy).remove::<InView>();
+            let Some(last_seen) = last_seen.filter(|last_seen| !is_seen(&last_seen.0)) else {
+                continue;
+            };
+            commands.spawn(ghost_bundle(
+                renderable.sprite_index,
+                name.clone(),
+                last_seen.0,
+                enemy,
+            ));
         }
     }
 }
 
-fn update_fov(
+#[allow(clippy::type_complexity)]
+pub fn spot_items(
+    mut commands: Commands,
     map: Res<Map>,
-    mut query: Query<(&mut TextureAtlasSprite, &Transform), Or<(With<MapTile>, With<Enemy>)>>,
+    item_query: Query<(Entity, &Position), (With<Item>, Without<Spotted>)>,
 ) {
-    for (mut sprite, transform) in query.iter_mut() {
-        let x = transform.translation.x as usize / 12;
-        let y = transform.translation.y as usize / 12;
-        sprite.color = match map.get(x, y).unwrap().view_status {
-            ViewStatus::Seen => Color::WHITE,
-            ViewStatus::Revealed => Color::GRAY,
-            ViewStatus::Unexplored => Color::BLACK,
-        };
-    }
-}
-
-//Port of shadow-casting code from here: https://www.roguebasin.com/index.php/Python_shadowcasting_implementation
-fn calculate_fov(mut map: ResMut<Map>, query: Query<&Transform, With<Player>>) {
-    const MULTIPLIERS: [[isize; 8]; 4] = [
-        [1, 0, 0, -1, -1, 0, 0, 1],
-        [0, 1, -1, 0, 0, -1, 1, 0],
-        [0, 1, 1, 0, 0, -1, -1, 0],
-        [1, 0, 0, 1, -1, 0, 0, -1],
-    ];
+    for (item, position) in item_query.iter() {
+        if map
+            .get(position.x, position.y)
+            .is_some_and(|tile| tile.view_status == ViewStatus::Seen)
+        {
+            commands.entity(item).insert(Spotted);
+        }
+    }
+}
 
-    let radius = 15;
-    let player_transform = query.single();
-    let x0 = player_transform.translation.x as isize / 12;
-    let y0 = player_transform.translation.y as isize / 12;
-
-    for octant in 0..8 {
-        cast_light(
-            &mut map,
-            x0,
-            y0,
-            1,
-            1.0,
-            0.0,
-            radius,
-            MULTIPLIERS[0][octant],
-            MULTIPLIERS[1][octant],
-            MULTIPLIERS[2][octant],
-            MULTIPLIERS[3][octant],
-            0,
-        );
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn cast_light(
-    map: &mut Map,
-    x0: isize,
-    y0: isize,
-    row: isize,
-    mut start: f32,
-    end: f32,
-    radius: isize,
-    xx: isize,
-    xy: isize,
-    yx: isize,
-    yy: isize,
-    _depth: usize,
+pub fn ghost_bundle(
+    sprite_index: usize,
+    name: Name,
+    position: Position,
+    enemy: Entity,
+) -> impl Bundle {
+    (
+        Renderable {
+            sprite_index,
+            layer: 0.75,
+        },
+        Ghost { enemy },
+        name,
+        position,
+    )
+}
+
+pub fn calculate_fov(
+    mut map: ResMut<Map>,
+    algorithm: Res<FovAlgorithm>,
+    light_map: Option<Res<LightMap>>,
+    mut changes: ResMut<FovChanges>,
+    mut query: Query<(Ref<Position>, &mut Viewshed), With<Player>>,
 ) {
-    if start < end {
+    changes.tiles.clear();
+    let Ok((position, mut viewshed)) = query.get_single_mut() else {
+        return;
+    };
+    if !position.is_changed()
+        && !viewshed.is_changed()
+        && !algorithm.is_changed()
+        && !light_map
+            .as_ref()
+            .is_some_and(|light_map| light_map.is_changed())
+        && viewshed.layout_version == Some(map.layout_version())
+    {
         return;
     }
+    //the viewshed is only written to here, so bypass change detection to avoid recalculating next frame
+    let viewshed = viewshed.bypass_change_detection();
+    let origin = (position.x, position.y);
+    let mut visible = compute_fov(&map, origin, viewshed.radius, *algorithm);
+    //tiles in darkness can't be seen beyond the viewer's own light
+    if let Some(light_map) = &light_map {
+        visible.retain(|&(x, y)| (x, y) == origin || light_map.is_lit(x, y));
+    }
 
-    let radius_squared = radius * radius;
-    for j in row..=radius {
-        let (mut dx, dy) = (-j - 1, -j);
-        let mut blocked = false;
-        let mut new_start = 0.0;
-        while dx <= 0 {
-            dx += 1;
-            //Translate the dx, dy coordinates into map coordinates:
-            let (x, y) = (x0 + dx * xx + dy * xy, y0 + dx * yx + dy * yy);
-            // l_slope and r_slope store the slopes of the left and right
-            // extremities of the square we're considering:
-            let (l_slope, r_slope) = (
-                (dx as f32 - 0.5) / (dy as f32 + 0.5),
-                (dx as f32 + 0.5) / (dy as f32 - 0.5),
-            );
-            if start < r_slope {
-                continue;
-            } else if end > l_slope {
-                break;
-            } else {
-                // Our light beam is touching this square; light it:
-                if dx * dx + dy * dy < radius_squared && x > 0 && y > 0 {
-                    if let Some(tile) = map.get_mut(x as usize, y as usize) {
-                        tile.view_status = ViewStatus::Seen;
+    //a regenerated map starts out unexplored, so only demote tiles that are still marked as seen
+    for &(x, y) in viewshed.visible.difference(&visible) {
+        if map.get(x, y).unwrap().view_status == ViewStatus::Seen {
+            map.get_mut(x, y).unwrap().view_status = ViewStatus::Revealed;
+            changes.tiles.insert((x, y));
+        }
+    }
+    for &(x, y) in visible.iter() {
+        if map.get(x, y).unwrap().view_status != ViewStatus::Seen {
+            map.get_mut(x, y).unwrap().view_status = ViewStatus::Seen;
+            changes.tiles.insert((x, y));
+        }
+    }
+
+    viewshed.visible = visible;
+    viewshed.layout_version = Some(map.layout_version());
+}
+
+//Every tile visible from the origin within the radius, including the origin itself.
+//Walls are visible but block sight; anything outside the map counts as a wall.
+pub fn compute_fov(
+    map: &Map,
+    origin: (usize, usize),
+    radius: usize,
+    algorithm: FovAlgorithm,
+) -> HashSet<(usize, usize)> {
+    let mut visible = HashSet::new();
+    if map.get(origin.0, origin.1).is_none() {
+        return visible;
+    }
+    visible.insert(origin);
+
+    let (x0, y0) = (origin.0 as isize, origin.1 as isize);
+    let radius = radius as isize;
+    match algorithm {
+        FovAlgorithm::SymmetricShadowcasting => {
+            for quadrant in Quadrant::ALL {
+                let row = Row {
+                    depth: 1,
+                    start: Slope::new(-1, 1),
+                    end: Slope::new(1, 1),
+                };
+                scan_row(map, &mut visible, (x0, y0), quadrant, radius, row);
+            }
+        }
+        FovAlgorithm::Permissive => {
+            for x in x0 - radius..=x0 + radius {
+                for y in y0 - radius..=y0 + radius {
+                    if in_radius(x - x0, y - y0, radius)
+                        && x >= 0
+                        && y >= 0
+                        && map.get(x as usize, y as usize).is_some()
+                        && has_permissive_line(map, (x0, y0), (x, y))
+                    {
+                        visible.insert((x as usize, y as usize));
                     }
                 }
-                if blocked {
-                    // we're scanning a row of blocked squares:
-                    if is_blocked(map, x, y) {
-                        new_start = r_slope;
-                        continue;
-                    } else {
-                        blocked = false;
-                        start = new_start;
+            }
+        }
+        FovAlgorithm::Raycasting => {
+            let clamp = |x: isize, y: isize| {
+                (
+                    x.clamp(0, map.width as isize - 1) as usize,
+                    y.clamp(0, map.height as isize - 1) as usize,
+                )
+            };
+            let edge = (-radius..=radius)
+                .flat_map(|i| [(i, -radius), (i, radius), (-radius, i), (radius, i)]);
+            for (dx, dy) in edge {
+                for (x, y) in Map::line(origin, clamp(x0 + dx, y0 + dy)) {
+                    if !in_radius(x as isize - x0, y as isize - y0, radius) {
+                        break;
+                    }
+                    visible.insert((x, y));
+                    if is_blocked(map, x as isize, y as isize) {
+                        break;
                     }
-                } else if is_blocked(map, x, y) && j < radius {
-                    // This is a blocking square, start a child scan:
-                    blocked = true;
-                    cast_light(
-                        map,
-                        x0,
-                        y0,
-                        j + 1,
-                        start,
-                        l_slope,
-                        radius,
-                        xx,
-                        xy,
-                        yx,
-                        yy,
-                        _depth + 1,
-                    );
-                    new_start = r_slope
                 }
             }
         }
-        if blocked {
-            break;
-        }
     }
+    visible
+}
+
+fn in_radius(dx: isize, dy: isize, radius: isize) -> bool {
+    dx * dx + dy * dy <= radius * radius
 }
 
 fn is_blocked(map: &Map, x: isize, y: isize) -> bool {
-    return x < 0
+    x < 0
         || y < 0
         || if let Some(tile) = map.get(x as usize, y as usize) {
             !tile.passable
         } else {
             true
-        };
+        }
+}
+
+//Symmetric shadowcasting, following https://www.albertford.com/shadowcasting/
+//Slopes are kept as exact fractions so that rounding can't break symmetry.
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    const ALL: [Quadrant; 4] = [
+        Quadrant::North,
+        Quadrant::East,
+        Quadrant::South,
+        Quadrant::West,
+    ];
+
+    fn transform(&self, origin: (isize, isize), depth: isize, col: isize) -> (isize, isize) {
+        let (x0, y0) = origin;
+        match self {
+            Quadrant::North => (x0 + col, y0 - depth),
+            Quadrant::South => (x0 + col, y0 + depth),
+            Quadrant::East => (x0 + depth, y0 + col),
+            Quadrant::West => (x0 - depth, y0 + col),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Slope {
+    numerator: isize,
+    //always positive
+    denominator: isize,
+}
+
+impl Slope {
+    fn new(numerator: isize, denominator: isize) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    //slope of the left edge of the tile at this column and depth
+    fn of_tile(depth: isize, col: isize) -> Self {
+        Self::new(2 * col - 1, 2 * depth)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Row {
+    depth: isize,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    //columns covered by the row, rounding half-covered tiles inwards at the end and outwards at the start
+    fn columns(&self) -> std::ops::RangeInclusive<isize> {
+        let (start, end) = (self.start, self.end);
+        let min_col = (2 * self.depth * start.numerator + start.denominator)
+            .div_euclid(2 * start.denominator);
+        let max_col =
+            -(-(2 * self.depth * end.numerator - end.denominator)).div_euclid(2 * end.denominator);
+        min_col..=max_col
+    }
+
+    //whether the tile's centre lies between the row's start and end slopes
+    fn is_symmetric(&self, col: isize) -> bool {
+        col * self.start.denominator >= self.depth * self.start.numerator
+            && col * self.end.denominator <= self.depth * self.end.numerator
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+fn scan_row(
+    map: &Map,
+    visible: &mut HashSet<(usize, usize)>,
+    origin: (isize, isize),
+    quadrant: Quadrant,
+    radius: isize,
+    mut row: Row,
+) {
+    if row.depth > radius {
+        return;
+    }
+
+    let mut previous_blocked = None;
+    for col in row.columns() {
+        let (x, y) = quadrant.transform(origin, row.depth, col);
+        let blocked = is_blocked(map, x, y);
+        if (blocked || row.is_symmetric(col))
+            && in_radius(row.depth, col, radius)
+            && x >= 0
+            && y >= 0
+            && map.get(x as usize, y as usize).is_some()
+        {
+            visible.insert((x as usize, y as usize));
+        }
+        if previous_blocked == Some(true) && !blocked {
+            row.start = Slope::of_tile(row.depth, col);
+        }
+        if previous_blocked == Some(false) && blocked {
+            let mut next_row = row.next();
+            next_row.end = Slope::of_tile(row.depth, col);
+            scan_row(map, visible, origin, quadrant, radius, next_row);
+        }
+        previous_blocked = Some(blocked);
+    }
+    if previous_blocked == Some(false) {
+        scan_row(map, visible, origin, quadrant, radius, row.next());
+    }
+}
+
+//Points inside a tile that permissive lines are drawn between, relative to its centre
+const PERMISSIVE_SAMPLES: [(f64, f64); 5] = [
+    (0.0, 0.0),
+    (-0.4, -0.4),
+    (0.4, -0.4),
+    (-0.4, 0.4),
+    (0.4, 0.4),
+];
+
+fn has_permissive_line(map: &Map, a: (isize, isize), b: (isize, isize)) -> bool {
+    //always trace from the same end so that the result is symmetric
+    let (a, b) = if a <= b { (a, b) } else { (b, a) };
+    PERMISSIVE_SAMPLES.iter().any(|from| {
+        PERMISSIVE_SAMPLES.iter().any(|to| {
+            let from = (a.0 as f64 + from.0, a.1 as f64 + from.1);
+            let to = (b.0 as f64 + to.0, b.1 as f64 + to.1);
+            is_segment_clear(map, from, to, a, b)
+        })
+    })
+}
+
+//Walks every tile the segment passes through, failing on any blocked tile between its ends.
+//Passing exactly through a corner doesn't touch the tiles either side of it.
+fn is_segment_clear(
+    map: &Map,
+    from: (f64, f64),
+    to: (f64, f64),
+    start: (isize, isize),
+    end: (isize, isize),
+) -> bool {
+    let axis = |from: f64, to: f64, cell: isize| {
+        let delta = to - from;
+        if delta > 0.0 {
+            (1, (cell as f64 + 0.5 - from) / delta, 1.0 / delta)
+        } else if delta < 0.0 {
+            (-1, (cell as f64 - 0.5 - from) / delta, -1.0 / delta)
+        } else {
+            (0, f64::INFINITY, f64::INFINITY)
+        }
+    };
+    let (step_x, mut t_max_x, t_delta_x) = axis(from.0, to.0, start.0);
+    let (step_y, mut t_max_y, t_delta_y) = axis(from.1, to.1, start.1);
+
+    let (mut x, mut y) = start;
+    let max_steps = start.0.abs_diff(end.0) + start.1.abs_diff(end.1);
+    for _ in 0..max_steps {
+        if (x, y) == end {
+            return true;
+        }
+        if (t_max_x - t_max_y).abs() < 1e-9 {
+            x += step_x;
+            y += step_y;
+            t_max_x += t_delta_x;
+            t_max_y += t_delta_y;
+        } else if t_max_x < t_max_y {
+            x += step_x;
+            t_max_x += t_delta_x;
+        } else {
+            y += step_y;
+            t_max_y += t_delta_y;
+        }
+        if (x, y) != end && is_blocked(map, x, y) {
+            return false;
+        }
+    }
+    (x, y) == end
 }
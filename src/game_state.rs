@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    actor::{Enemy, Player, TurnCount, TurnState},
+    combat::Dead,
+    fov::Ghost,
+    item::Item,
+    level_generation::{generators::MapGeneratorSettings, map::Map, Depth},
+    lighting::LightSource,
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction},
+    position::Position,
+    replay::{Replay, ReplayPlayback},
+    rng::GameRng,
+    save::SaveSlot,
+};
+
+//Taking the stairs down from this depth wins the game
+pub const MAX_DEPTH: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, States)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    //resetting everything left over from the previous run
+    NewGame,
+    //generating a level and spawning everything on it, at the start of a run or after descending
+    LoadingLevel,
+    //replacing everything with the contents of the saved game
+    LoadingSave,
+    Playing,
+    Paused,
+    GameOver,
+    Victory,
+}
+
+//Seed for the next run started, in place of a random one
+#[derive(Resource)]
+pub struct NextRunSeed(pub u64);
+
+//Systems that only run while the game is being played, not in menus or while paused
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GameplaySet;
+
+//Steps taken on entering AppState::LoadingLevel, in order
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum LevelSet {
+    Generate,
+    Spawn,
+}
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .init_resource::<GameRng>()
+            .init_resource::<MapGeneratorSettings>()
+            .configure_set(Update, GameplaySet.run_if(in_state(AppState::Playing)))
+            .configure_sets(
+                OnEnter(AppState::LoadingLevel),
+                (LevelSet::Generate, LevelSet::Spawn).chain(),
+            )
+            .add_systems(
+                OnEnter(AppState::MainMenu),
+                (despawn_level, despawn_run, remove_map),
+            )
+            .add_systems(OnEnter(AppState::NewGame), (despawn_run, start_new_run))
+            .add_systems(
+                OnEnter(AppState::LoadingLevel),
+                (
+                    (despawn_level, generate_level).in_set(LevelSet::Generate),
+                    apply_deferred
+                        .after(LevelSet::Generate)
+                        .before(LevelSet::Spawn),
+                    finish_loading.after(LevelSet::Spawn),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    descend_stairs
+                        .after(take_player_action)
+                        .run_if(state_exists_and_equals(TurnState::Player)),
+                    end_game_on_death,
+                )
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Everything belonging to the current level, but not the player or what they carry
+#[allow(clippy::type_complexity)]
+pub fn despawn_level(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        Or<(
+            With<Enemy>,
+            With<Ghost>,
+            (With<Item>, With<Position>),
+            (With<LightSource>, Without<Player>),
+        )>,
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+//The player and their items
+#[allow(clippy::type_complexity)]
+pub fn despawn_run(
+    mut commands: Commands,
+    query: Query<Entity, Or<(With<Player>, (With<Item>, Without<Position>))>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn remove_map(mut commands: Commands) {
+    commands.remove_resource::<Map>();
+}
+
+fn start_new_run(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    next_seed: Option<Res<NextRunSeed>>,
+    mut slot: ResMut<SaveSlot>,
+    mut next_turn_state: ResMut<NextState<TurnState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    commands.insert_resource(Depth::default());
+    commands.insert_resource(TurnCount::default());
+    //a replay is played back on the run it was recorded from
+    let seed = match (&playback, next_seed) {
+        (Some(playback), _) => playback.seed,
+        (None, Some(next_seed)) => next_seed.0,
+        (None, None) => rand::thread_rng().gen(),
+    };
+    commands.remove_resource::<NextRunSeed>();
+    let mut message_log = MessageLog::default();
+    if playback.is_some() {
+        message_log.push(
+            MessageKind::Info,
+            "Watching a replay. Your input is ignored until it ends.",
+        );
+    }
+    commands.insert_resource(message_log);
+    commands.insert_resource(GameRng::from_seed(seed));
+    commands.insert_resource(Replay::new(seed));
+    slot.error = None;
+    next_turn_state.set(TurnState::Player);
+    next_state.set(AppState::LoadingLevel);
+}
+
+fn generate_level(
+    mut commands: Commands,
+    settings: Res<MapGeneratorSettings>,
+    mut rng: ResMut<GameRng>,
+) {
+    commands.insert_resource(Map::new(*settings, &mut rng.0));
+}
+
+fn finish_loading(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Playing);
+}
+
+fn descend_stairs(
+    mut actions: EventReader<PlayerAction>,
+    player_query: Query<&Position, With<Player>>,
+    map: Res<Map>,
+    mut depth: ResMut<Depth>,
+    mut message_log: ResMut<MessageLog>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !actions
+        .iter()
+        .any(|action| *action == PlayerAction::Descend)
+    {
+        return;
+    }
+    let Ok(position) = player_query.get_single() else {
+        return;
+    };
+    if !map
+        .get(position.x, position.y)
+        .is_some_and(|tile| tile.stairs)
+    {
+        message_log.push(MessageKind::Warning, "There are no stairs here.");
+        return;
+    }
+
+    if depth.0 >= MAX_DEPTH {
+        next_state.set(AppState::Victory);
+    } else {
+        depth.0 += 1;
+        message_log.push(MessageKind::Info, "You descend deeper into the caves.");
+        next_state.set(AppState::LoadingLevel);
+    }
+}
+
+fn end_game_on_death(
+    query: Query<(), (With<Player>, Added<Dead>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !query.is_empty() {
+        next_state.set(AppState::GameOver);
+    }
+}
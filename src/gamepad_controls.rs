@@ -0,0 +1,256 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiInput, EguiSet};
+
+use crate::{
+    actor::{Player, TurnState},
+    auto_explore::{explore, AutoExplore},
+    game_state::{AppState, GameplaySet},
+    key_bindings::{REPEAT_DELAY, REPEAT_INTERVAL},
+    level_generation::map::Map,
+    look::LookCursor,
+    player_action::{take_player_action, PlayerAction, PlayerInput},
+    position::{Position, PositionDelta},
+    rest::Rest,
+    targeting::TargetSelection,
+    travel::Travel,
+    travel_view::TravelMenu,
+    ui::InventoryScreen,
+};
+
+//How far a stick has to be pushed before it counts as pointing somewhere
+const STICK_DEADZONE: f32 = 0.5;
+
+//Lets the whole game be played with a gamepad. Menus are driven through egui's keyboard
+//navigation, so they need nothing gamepad specific of their own.
+pub struct GamepadControlsPlugin;
+
+impl Plugin for GamepadControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            navigate_menus
+                .after(EguiSet::ProcessInput)
+                .before(EguiSet::BeginFrame),
+        )
+        .add_systems(
+            Update,
+            gamepad_input
+                .before(explore)
+                .before(take_player_action)
+                .run_if(state_exists_and_equals(TurnState::Player))
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+//Buttons and sticks of every connected gamepad, read as if they were one
+#[derive(SystemParam)]
+pub struct GamepadInput<'w> {
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl GamepadInput<'_> {
+    pub fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.buttons
+                .just_pressed(GamepadButton::new(gamepad, button_type))
+        })
+    }
+
+    pub fn pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.buttons
+                .pressed(GamepadButton::new(gamepad, button_type))
+        })
+    }
+
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                Vec2::new(
+                    self.axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.),
+                    self.axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.),
+                )
+            })
+            .max_by(|a, b| a.length().total_cmp(&b.length()))
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    pub fn left_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY)
+    }
+
+    //Direction held on the d-pad, or else the one the left stick points in
+    pub fn direction(&self) -> Option<PositionDelta> {
+        let x = self.pressed(GamepadButtonType::DPadRight) as isize
+            - self.pressed(GamepadButtonType::DPadLeft) as isize;
+        let y = self.pressed(GamepadButtonType::DPadUp) as isize
+            - self.pressed(GamepadButtonType::DPadDown) as isize;
+        if x != 0 || y != 0 {
+            return Some(PositionDelta::new(x, y));
+        }
+        snap_direction(self.left_stick())
+    }
+}
+
+//Snaps a stick to the nearest of the eight directions, ignoring it inside the deadzone
+pub fn snap_direction(stick: Vec2) -> Option<PositionDelta> {
+    if stick.length() < STICK_DEADZONE {
+        return None;
+    }
+    let angle = (stick.y.atan2(stick.x) / FRAC_PI_4).round() * FRAC_PI_4;
+    Some(PositionDelta::new(
+        angle.cos().round() as isize,
+        angle.sin().round() as isize,
+    ))
+}
+
+//Direction being held, repeated like a held down key
+#[derive(Default)]
+struct DirectionRepeat {
+    direction: Option<PositionDelta>,
+    timer: Timer,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gamepad_input(
+    gamepad: GamepadInput,
+    time: Res<Time>,
+    map: Res<Map>,
+    player_query: Query<&Position, With<Player>>,
+    mut inventory_screen: ResMut<InventoryScreen>,
+    mut travel_menu: ResMut<TravelMenu>,
+    selection: Res<TargetSelection>,
+    mut look_cursor: ResMut<LookCursor>,
+    mut repeat: Local<DirectionRepeat>,
+    mut auto_explore: ResMut<AutoExplore>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    //open windows take the gamepad over, see navigate_menus
+    if inventory_screen.open || travel_menu.open {
+        if gamepad.just_pressed(GamepadButtonType::East) {
+            inventory_screen.open = false;
+            travel_menu.open = false;
+        }
+        *repeat = DirectionRepeat::default();
+        return;
+    }
+
+    let direction = gamepad.direction();
+    let step = if direction != repeat.direction {
+        *repeat = DirectionRepeat {
+            direction,
+            timer: Timer::from_seconds(REPEAT_DELAY, TimerMode::Once),
+        };
+        direction
+    } else if direction.is_some() && repeat.timer.tick(time.delta()).finished() {
+        repeat.timer = Timer::from_seconds(REPEAT_INTERVAL, TimerMode::Once);
+        direction
+    } else {
+        None
+    };
+
+    if let Some(cursor) = look_cursor.position {
+        if let Some(delta) = step {
+            let moved = cursor + delta;
+            if map.get(moved.x, moved.y).is_some() {
+                look_cursor.position = Some(moved);
+            }
+        }
+        if gamepad.just_pressed(GamepadButtonType::East)
+            || gamepad.just_pressed(GamepadButtonType::LeftThumb)
+        {
+            look_cursor.position = None;
+        }
+        return;
+    }
+    //aiming has the face buttons to itself, see select_target
+    if selection.pending.is_some() {
+        return;
+    }
+
+    if let Some(delta) = step {
+        inputs.send(PlayerInput(PlayerAction::Move(delta)));
+    }
+    if gamepad.just_pressed(GamepadButtonType::South) {
+        inputs.send(PlayerInput(PlayerAction::PickUp));
+    }
+    if gamepad.just_pressed(GamepadButtonType::East) {
+        inputs.send(PlayerInput(PlayerAction::Wait));
+    }
+    if gamepad.just_pressed(GamepadButtonType::West) {
+        inventory_screen.open = true;
+    }
+    if gamepad.just_pressed(GamepadButtonType::North) {
+        inputs.send(PlayerInput(PlayerAction::Descend));
+    }
+    if gamepad.just_pressed(GamepadButtonType::RightThumb) {
+        travel_menu.open = true;
+    }
+    if gamepad.just_pressed(GamepadButtonType::LeftThumb) {
+        look_cursor.position = player_query.get_single().ok().copied();
+    }
+
+    if gamepad.just_pressed(GamepadButtonType::Select) {
+        let active = auto_explore.active;
+        travel.stop();
+        rest.stop();
+        if active {
+            auto_explore.stop();
+        } else {
+            auto_explore.start();
+        }
+    }
+    if gamepad.just_pressed(GamepadButtonType::LeftTrigger2) {
+        auto_explore.stop();
+        travel.stop();
+        rest.start();
+    }
+}
+
+//Turns the d-pad and the south button into the keys egui moves focus and clicks buttons with,
+//while a menu or a window is open
+fn navigate_menus(
+    gamepad: GamepadInput,
+    state: Res<State<AppState>>,
+    inventory_screen: Res<InventoryScreen>,
+    travel_menu: Res<TravelMenu>,
+    mut egui_input_query: Query<&mut EguiInput, With<PrimaryWindow>>,
+) {
+    let in_menu = *state.get() != AppState::Playing;
+    if !in_menu && !inventory_screen.open && !travel_menu.open {
+        return;
+    }
+    let Ok(mut egui_input) = egui_input_query.get_single_mut() else {
+        return;
+    };
+
+    let mut press = |key, shift| {
+        egui_input.events.push(egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers { shift, ..default() },
+        });
+    };
+    if gamepad.just_pressed(GamepadButtonType::DPadDown) {
+        press(egui::Key::Tab, false);
+    }
+    if gamepad.just_pressed(GamepadButtonType::DPadUp) {
+        press(egui::Key::Tab, true);
+    }
+    if gamepad.just_pressed(GamepadButtonType::South) {
+        press(egui::Key::Enter, false);
+    }
+}
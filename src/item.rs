@@ -0,0 +1,516 @@
+use bevy::prelude::*;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::Index;
+
+use crate::{
+    actor::{Actor, CombatStats, Player, TurnState},
+    effect::{Effect, EffectEvent, Targeting},
+    game_state::{AppState, GameplaySet, LevelSet},
+    level_generation::map::Map,
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction},
+    position::Position,
+    rng::GameRng,
+    sprite_atlas::Renderable,
+};
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        //the web build can't read files, so it keeps the definitions built into the game
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Startup, load_item_definitions);
+
+        app.init_resource::<ItemDefinitions>()
+            .add_systems(
+                OnEnter(AppState::LoadingLevel),
+                spawn_items.in_set(LevelSet::Spawn),
+            )
+            .add_systems(
+                Update,
+                (
+                    (pickup_items, handle_inventory_actions)
+                        .after(take_player_action)
+                        .run_if(state_exists_and_equals(TurnState::Player)),
+                    apply_equipment_bonuses,
+                )
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct EquipmentDefinition {
+    pub slot: EquipmentSlot,
+    //stats added to the wearer's while this item is equipped
+    pub bonus: CombatStats,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RangedDefinition {
+    pub range: usize,
+    pub damage: f32,
+    //name of the item used up by each shot
+    #[serde(default)]
+    pub ammo: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ItemDefinition {
+    pub name: String,
+    pub sprite_index: usize,
+    //largest count a single stack of this kind can hold, 1 for unstackable items
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    //relative chance of this item being picked at an item spawn point
+    #[serde(default)]
+    pub spawn_weight: u32,
+    //uses before the item is used up, items without charges are consumed one per use
+    #[serde(default)]
+    pub charges: Option<u32>,
+    #[serde(default)]
+    pub equipment: Option<EquipmentDefinition>,
+    //lets an equipped weapon be fired
+    #[serde(default)]
+    pub ranged: Option<RangedDefinition>,
+    #[serde(default)]
+    pub targeting: Targeting,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+impl ItemDefinition {
+    pub fn is_usable(&self) -> bool {
+        !self.effects.is_empty()
+    }
+
+    //"a sword", "an arrow", "3 potions of haste"
+    pub fn describe(&self, count: u32) -> String {
+        if count == 1 {
+            let article = if self.name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                "an"
+            } else {
+                "a"
+            };
+            return format!("{article} {}", self.name);
+        }
+        let plural = match self.name.split_once(" of ") {
+            Some((noun, rest)) => format!("{noun}s of {rest}"),
+            None => format!("{}s", self.name),
+        };
+        format!("{count} {plural}")
+    }
+}
+
+//Index into ItemDefinitions
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ItemKind(pub usize);
+
+#[derive(Resource)]
+pub struct ItemDefinitions {
+    pub definitions: Vec<ItemDefinition>,
+}
+
+pub const ITEMS_PATH: &str = "items.ron";
+
+//The copy of assets/items.ron built into the game, used until the file itself is read
+impl Default for ItemDefinitions {
+    fn default() -> Self {
+        Self::parse(include_str!("../assets/items.ron"))
+            .expect("assets/items.ron should contain valid item definitions")
+    }
+}
+
+impl ItemDefinitions {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let definitions: Vec<ItemDefinition> =
+            ron::from_str(contents).map_err(|error| error.to_string())?;
+        if definitions.is_empty() {
+            return Err("there are no items".to_string());
+        }
+        if let Some(definition) = definitions
+            .iter()
+            .find(|definition| definition.max_stack == 0)
+        {
+            return Err(format!("{} has a max_stack of 0", definition.name));
+        }
+        if definitions
+            .iter()
+            .all(|definition| definition.spawn_weight == 0)
+        {
+            return Err("no item has a spawn_weight above 0".to_string());
+        }
+        Ok(Self { definitions })
+    }
+
+    pub fn find(&self, name: &str) -> Option<ItemKind> {
+        self.definitions
+            .iter()
+            .position(|definition| definition.name == name)
+            .map(ItemKind)
+    }
+}
+
+impl Index<ItemKind> for ItemDefinitions {
+    type Output = ItemDefinition;
+
+    fn index(&self, kind: ItemKind) -> &ItemDefinition {
+        &self.definitions[kind.0]
+    }
+}
+
+//Items lying on the floor have a Position, carried items don't
+#[derive(Component, Clone, Copy)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub count: u32,
+    pub charges: Option<u32>,
+}
+
+#[derive(Component)]
+pub struct Inventory {
+    pub items: Vec<Entity>,
+    pub capacity: usize,
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.items.retain(|held_entity| *held_entity != entity);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armour,
+}
+
+//Equipped items stay in the inventory, the slots only reference them
+#[derive(Component, Default)]
+pub struct Equipment {
+    pub weapon: Option<Entity>,
+    pub armour: Option<Entity>,
+}
+
+impl Equipment {
+    pub fn slot(&self, slot: EquipmentSlot) -> Option<Entity> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon,
+            EquipmentSlot::Armour => self.armour,
+        }
+    }
+
+    pub fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<Entity> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armour => &mut self.armour,
+        }
+    }
+
+    pub fn is_equipped(&self, entity: Entity) -> bool {
+        self.weapon == Some(entity) || self.armour == Some(entity)
+    }
+
+    pub fn unequip(&mut self, entity: Entity) {
+        if self.weapon == Some(entity) {
+            self.weapon = None;
+        }
+        if self.armour == Some(entity) {
+            self.armour = None;
+        }
+    }
+}
+
+//Items are referred to by their slot in the player's inventory
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum InventoryAction {
+    //targeted items carry the tile the player picked
+    Use(usize, Option<Position>),
+    Drop(usize),
+    Equip(usize),
+    Unequip(usize),
+}
+
+impl InventoryAction {
+    pub fn slot(&self) -> usize {
+        match *self {
+            InventoryAction::Use(slot, _)
+            | InventoryAction::Drop(slot)
+            | InventoryAction::Equip(slot)
+            | InventoryAction::Unequip(slot) => slot,
+        }
+    }
+}
+
+//Reads the item definitions from the assets folder, so they can be changed without a rebuild.
+//A file that can't be read or won't parse leaves the built in copy in place.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_item_definitions(mut item_definitions: ResMut<ItemDefinitions>) {
+    let path = bevy::asset::FileAssetIo::get_base_path()
+        .join("assets")
+        .join(ITEMS_PATH);
+    let loaded = std::fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| ItemDefinitions::parse(&contents));
+    match loaded {
+        Ok(loaded) => *item_definitions = loaded,
+        Err(error) => warn!(
+            "Using the built in items, couldn't load {}: {error}",
+            path.display()
+        ),
+    }
+}
+
+fn spawn_items(
+    mut commands: Commands,
+    map: Res<Map>,
+    item_definitions: Res<ItemDefinitions>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = &mut rng.0;
+    let weights = WeightedIndex::new(
+        item_definitions
+            .definitions
+            .iter()
+            .map(|definition| definition.spawn_weight),
+    )
+    .unwrap();
+
+    for point in &map.item_spawn_points {
+        let kind = ItemKind(weights.sample(rng));
+        let definition = &item_definitions[kind];
+        let item = Item {
+            kind,
+            count: rng.gen_range(1..=definition.max_stack),
+            charges: definition.charges,
+        };
+        spawn_item(
+            &mut commands,
+            &item_definitions,
+            item,
+            Some(Position::new(point.0, point.1)),
+        );
+    }
+}
+
+//Spawns an item on the floor at the given position, or in an inventory without one
+pub fn spawn_item(
+    commands: &mut Commands,
+    item_definitions: &ItemDefinitions,
+    item: Item,
+    position: Option<Position>,
+) -> Entity {
+    let mut entity = commands.spawn((
+        Renderable {
+            sprite_index: item_definitions[item.kind].sprite_index,
+            layer: 0.5,
+        },
+        item,
+    ));
+    if let Some(position) = position {
+        entity.insert(position);
+    }
+    entity.id()
+}
+
+fn pickup_items(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerAction>,
+    mut player_query: Query<(&Position, &mut Inventory), With<Player>>,
+    mut item_query: Query<(Entity, &mut Item, Option<&Position>)>,
+    item_definitions: Res<ItemDefinitions>,
+    mut message_log: ResMut<MessageLog>,
+    mut next_state: ResMut<NextState<TurnState>>,
+) {
+    if !actions.iter().any(|action| *action == PlayerAction::PickUp) {
+        return;
+    }
+    let (player_position, mut inventory) = player_query.single_mut();
+
+    let ground_items: Vec<Entity> = item_query
+        .iter()
+        .filter(|(_, _, position)| *position == Some(player_position))
+        .map(|(entity, _, _)| entity)
+        .collect();
+    if ground_items.is_empty() {
+        return;
+    }
+
+    let mut picked_up = false;
+    for entity in ground_items {
+        let (_, item, _) = item_query.get(entity).unwrap();
+        let (kind, mut count) = (item.kind, item.count);
+        let ground_count = count;
+
+        //top up any partial stacks already being carried
+        for held_entity in &inventory.items {
+            let (_, mut held_item, _) = item_query.get_mut(*held_entity).unwrap();
+            if held_item.kind == kind {
+                let moved = count.min(
+                    item_definitions[kind]
+                        .max_stack
+                        .saturating_sub(held_item.count),
+                );
+                held_item.count += moved;
+                count -= moved;
+            }
+        }
+
+        if count > 0 {
+            item_query.get_mut(entity).unwrap().1.count = count;
+            if inventory.is_full() {
+                message_log.push(
+                    MessageKind::Warning,
+                    format!(
+                        "You have no room for {}.",
+                        item_definitions[kind].describe(count)
+                    ),
+                );
+            } else {
+                commands.entity(entity).remove::<Position>();
+                inventory.items.push(entity);
+                count = 0;
+            }
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        let taken = ground_count - count;
+        if taken > 0 {
+            message_log.push(
+                MessageKind::Info,
+                format!("You pick up {}.", item_definitions[kind].describe(taken)),
+            );
+            picked_up = true;
+        }
+    }
+
+    if picked_up {
+        next_state.set(TurnState::Enemy);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_inventory_actions(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerAction>,
+    mut effect_events: EventWriter<EffectEvent>,
+    mut player_query: Query<(Entity, &Position, &mut Inventory, &mut Equipment), With<Player>>,
+    mut item_query: Query<&mut Item>,
+    item_definitions: Res<ItemDefinitions>,
+    mut message_log: ResMut<MessageLog>,
+    mut next_state: ResMut<NextState<TurnState>>,
+) {
+    let (player, player_position, mut inventory, mut equipment) = player_query.single_mut();
+
+    let Some(action) = actions.iter().find_map(|action| match action {
+        PlayerAction::Inventory(action) => Some(*action),
+        _ => None,
+    }) else {
+        return;
+    };
+    let Some(entity) = inventory.items.get(action.slot()).copied() else {
+        return;
+    };
+
+    match action {
+        InventoryAction::Use(_, target) => {
+            let Ok(mut item) = item_query.get_mut(entity) else {
+                return;
+            };
+            let definition = &item_definitions[item.kind];
+            if !definition.is_usable() {
+                return;
+            }
+            effect_events.send(EffectEvent {
+                effects: definition.effects.clone(),
+                source: player,
+                target,
+            });
+
+            let used_up = match &mut item.charges {
+                Some(charges) => {
+                    *charges -= 1;
+                    *charges == 0
+                }
+                None => {
+                    item.count -= 1;
+                    item.count == 0
+                }
+            };
+            if used_up {
+                inventory.remove(entity);
+                equipment.unequip(entity);
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        InventoryAction::Drop(_) => {
+            inventory.remove(entity);
+            equipment.unequip(entity);
+            commands.entity(entity).insert(*player_position);
+            if let Ok(item) = item_query.get(entity) {
+                let description = item_definitions[item.kind].describe(item.count);
+                message_log.push(MessageKind::Info, format!("You drop {description}."));
+            }
+        }
+        InventoryAction::Equip(_) => {
+            let Some(equipment_definition) = item_query
+                .get(entity)
+                .ok()
+                .and_then(|item| item_definitions[item.kind].equipment)
+            else {
+                return;
+            };
+            *equipment.slot_mut(equipment_definition.slot) = Some(entity);
+            let name = &item_definitions[item_query.get(entity).unwrap().kind].name;
+            message_log.push(MessageKind::Info, format!("You equip the {name}."));
+        }
+        InventoryAction::Unequip(_) => {
+            if !equipment.is_equipped(entity) {
+                return;
+            }
+            equipment.unequip(entity);
+            if let Ok(item) = item_query.get(entity) {
+                let name = &item_definitions[item.kind].name;
+                message_log.push(MessageKind::Info, format!("You unequip the {name}."));
+            }
+        }
+    }
+
+    next_state.set(TurnState::Enemy);
+}
+
+fn apply_equipment_bonuses(
+    mut query: Query<(&Actor, &Equipment, &mut CombatStats), Changed<Equipment>>,
+    item_query: Query<&Item>,
+    item_definitions: Res<ItemDefinitions>,
+) {
+    for (actor, equipment, mut stats) in query.iter_mut() {
+        *stats = [equipment.weapon, equipment.armour]
+            .into_iter()
+            .flatten()
+            .filter_map(|entity| item_query.get(entity).ok())
+            .filter_map(|item| item_definitions[item.kind].equipment)
+            .fold(actor.base_stats, |stats, equipment| stats + equipment.bonus);
+    }
+}
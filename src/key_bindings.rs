@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::TurnState,
+    auto_explore::{explore, AutoExplore},
+    game_state::GameplaySet,
+    look::LookCursor,
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction, PlayerInput},
+    position::PositionDelta,
+    rest::Rest,
+    storage,
+    targeting::TargetSelection,
+    travel::Travel,
+};
+
+pub const BINDINGS_NAME: &str = "key_bindings.ron";
+
+//How long a movement or wait key has to be held before it starts repeating, and how often it
+//repeats after that, in seconds
+pub const REPEAT_DELAY: f32 = 0.3;
+pub const REPEAT_INTERVAL: f32 = 0.1;
+
+//Maps keys to what they do, so the rest of the game asks for actions instead of keys
+pub struct KeyBindingsPlugin;
+
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(KeyBindings::preset(Preset::default()))
+            .add_event::<WriteKeyBindings>()
+            .add_systems(Startup, load_key_bindings)
+            .add_systems(
+                Update,
+                (
+                    keyboard_input
+                        .before(explore)
+                        .before(take_player_action)
+                        .run_if(state_exists_and_equals(TurnState::Player))
+                        .in_set(GameplaySet),
+                    write_key_bindings.run_if(on_event::<WriteKeyBindings>()),
+                ),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum KeyAction {
+    MoveNorth,
+    MoveNorthEast,
+    MoveEast,
+    MoveSouthEast,
+    MoveSouth,
+    MoveSouthWest,
+    MoveWest,
+    MoveNorthWest,
+    Wait,
+    //waits turn after turn until healed or interrupted
+    Rest,
+    PickUp,
+    Descend,
+    AutoExplore,
+    Travel,
+    Inventory,
+    Look,
+    Fire,
+    Minimap,
+    //brings the camera back to the player after looking around
+    Recenter,
+    Pause,
+    //backs out of looking or targeting
+    Cancel,
+    //cycles through the enemies in view while targeting
+    NextTarget,
+    //fires or uses the item at the tile being targeted
+    Confirm,
+}
+
+impl KeyAction {
+    pub const MOVES: [KeyAction; 8] = [
+        KeyAction::MoveNorth,
+        KeyAction::MoveNorthEast,
+        KeyAction::MoveEast,
+        KeyAction::MoveSouthEast,
+        KeyAction::MoveSouth,
+        KeyAction::MoveSouthWest,
+        KeyAction::MoveWest,
+        KeyAction::MoveNorthWest,
+    ];
+
+    pub fn direction(&self) -> Option<PositionDelta> {
+        let (x, y) = match self {
+            KeyAction::MoveNorth => (0, 1),
+            KeyAction::MoveNorthEast => (1, 1),
+            KeyAction::MoveEast => (1, 0),
+            KeyAction::MoveSouthEast => (1, -1),
+            KeyAction::MoveSouth => (0, -1),
+            KeyAction::MoveSouthWest => (-1, -1),
+            KeyAction::MoveWest => (-1, 0),
+            KeyAction::MoveNorthWest => (-1, 1),
+            _ => return None,
+        };
+        Some(PositionDelta::new(x, y))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Preset {
+    #[default]
+    Wasd,
+    Numpad,
+    Vi,
+    Arrows,
+}
+
+//What the bindings file holds: a preset, plus any actions rebound on top of it
+#[derive(Serialize, Deserialize, Default)]
+struct BindingsFile {
+    preset: Preset,
+    #[serde(default)]
+    overrides: HashMap<KeyAction, Vec<KeyCode>>,
+}
+
+const BINDINGS_FILE_HEADER: &str = "\
+// Key bindings. The preset is one of Wasd, Numpad, Vi or Arrows. Any action can be rebound under
+// overrides, which replaces the preset's keys for it, for example:
+//     overrides: { Wait: [Space, Numpad5], Rest: [Z] },
+// Actions: MoveNorth, MoveNorthEast, MoveEast, MoveSouthEast, MoveSouth, MoveSouthWest, MoveWest,
+// MoveNorthWest, Wait, Rest, PickUp, Descend, AutoExplore, Travel, Inventory, Look, Fire, Minimap,
+// Recenter, Pause, Cancel, NextTarget, Confirm. Key names are those of bevy's KeyCode.
+// Delete this file to go back to the default keys.
+";
+
+#[derive(Resource)]
+pub struct KeyBindings {
+    keys: HashMap<KeyAction, Vec<KeyCode>>,
+}
+
+impl KeyBindings {
+    pub fn preset(preset: Preset) -> Self {
+        use KeyCode::*;
+        let (moves, wait, look, pick_up) = match preset {
+            Preset::Wasd => ([W, E, D, C, S, Z, A, Q], Space, L, G),
+            Preset::Numpad => (
+                [
+                    Numpad8, Numpad9, Numpad6, Numpad3, Numpad2, Numpad1, Numpad4, Numpad7,
+                ],
+                Numpad5,
+                L,
+                G,
+            ),
+            //as in nethack: s searches, which is waiting, and ; looks around
+            Preset::Vi => ([K, U, L, N, J, B, H, Y], S, Semicolon, Comma),
+            Preset::Arrows => (
+                [Up, PageUp, Right, PageDown, Down, End, Left, Home],
+                Space,
+                L,
+                G,
+            ),
+        };
+
+        let mut keys: HashMap<KeyAction, Vec<KeyCode>> = KeyAction::MOVES
+            .into_iter()
+            .zip(moves)
+            .map(|(action, key)| (action, vec![key]))
+            .collect();
+        keys.extend([
+            (KeyAction::Wait, vec![wait]),
+            (KeyAction::Rest, vec![R]),
+            (KeyAction::PickUp, vec![pick_up]),
+            (KeyAction::Descend, vec![Period]),
+            (KeyAction::AutoExplore, vec![X]),
+            (KeyAction::Travel, vec![T]),
+            (KeyAction::Inventory, vec![I]),
+            (KeyAction::Look, vec![look]),
+            (KeyAction::Fire, vec![F]),
+            (KeyAction::Minimap, vec![M]),
+            (KeyAction::Recenter, vec![V]),
+            (KeyAction::Pause, vec![P]),
+            (KeyAction::Cancel, vec![Escape]),
+            (KeyAction::NextTarget, vec![Tab]),
+            (KeyAction::Confirm, vec![Return]),
+        ]);
+        Self { keys }
+    }
+
+    pub fn keys(&self, action: KeyAction) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    //the keys bound to any of the actions, for hints such as "L/Escape: stop looking"
+    pub fn describe(&self, actions: &[KeyAction]) -> String {
+        let names: Vec<String> = actions
+            .iter()
+            .flat_map(|action| self.keys(*action))
+            .map(|key| format!("{key:?}"))
+            .collect();
+        names.join("/")
+    }
+
+    pub fn just_pressed(&self, keyboard: &Input<KeyCode>, action: KeyAction) -> bool {
+        keyboard.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    pub fn pressed(&self, keyboard: &Input<KeyCode>, action: KeyAction) -> bool {
+        keyboard.any_pressed(self.keys(action).iter().copied())
+    }
+
+    //combined direction of every movement key pressed this frame, so diagonals can still be
+    //made out of two keys
+    pub fn just_pressed_direction(&self, keyboard: &Input<KeyCode>) -> Option<PositionDelta> {
+        let delta = KeyAction::MOVES
+            .into_iter()
+            .filter(|action| self.just_pressed(keyboard, *action))
+            .filter_map(|action| action.direction())
+            .fold(PositionDelta::new(0, 0), |sum, delta| {
+                PositionDelta::new(sum.x + delta.x, sum.y + delta.y)
+            });
+        let delta = PositionDelta::new(delta.x.clamp(-1, 1), delta.y.clamp(-1, 1));
+        (delta.x != 0 || delta.y != 0).then_some(delta)
+    }
+}
+
+//Asks for the default bindings file to be written out for editing, see write_key_bindings
+#[derive(Event)]
+pub struct WriteKeyBindings;
+
+//Reads the bindings file, if the player has written one out
+fn load_key_bindings(mut bindings: ResMut<KeyBindings>) {
+    if !storage::exists(BINDINGS_NAME) {
+        return;
+    }
+
+    let file: Result<BindingsFile, String> = storage::read(BINDINGS_NAME)
+        .and_then(|contents| ron::from_str(&contents).map_err(|error| error.to_string()));
+    match file {
+        Ok(file) => {
+            *bindings = KeyBindings::preset(file.preset);
+            bindings.keys.extend(file.overrides);
+        }
+        Err(error) => warn!("Ignoring {BINDINGS_NAME}, using the default keys: {error}"),
+    }
+}
+
+//Writes out the default bindings file, leaving alone any the player already has
+fn write_key_bindings(
+    mut events: EventReader<WriteKeyBindings>,
+    mut message_log: ResMut<MessageLog>,
+) {
+    events.clear();
+    if storage::exists(BINDINGS_NAME) {
+        message_log.push(
+            MessageKind::Warning,
+            format!("{BINDINGS_NAME} already exists."),
+        );
+        return;
+    }
+    let result =
+        ron::ser::to_string_pretty(&BindingsFile::default(), ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(
+                    BINDINGS_NAME,
+                    &format!("{BINDINGS_FILE_HEADER}{contents}\n"),
+                )
+            });
+    match result {
+        Ok(()) => message_log.push(
+            MessageKind::Info,
+            format!("Wrote {BINDINGS_NAME}. Edit it and restart to change the keys."),
+        ),
+        Err(error) => message_log.push(
+            MessageKind::Danger,
+            format!("Couldn't write {BINDINGS_NAME}: {error}"),
+        ),
+    }
+}
+
+//Movement or waiting being repeated while its keys are held down
+#[derive(Default)]
+struct KeyRepeat {
+    held: Vec<KeyAction>,
+    action: Option<PlayerAction>,
+    timer: Timer,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn keyboard_input(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    look_cursor: Res<LookCursor>,
+    target_selection: Res<TargetSelection>,
+    mut repeat: Local<KeyRepeat>,
+    mut auto_explore: ResMut<AutoExplore>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    let pressed = |action| bindings.just_pressed(&keyboard, action);
+
+    //the movement keys move the look cursor instead while it's out, and the player can't walk off
+    //while aiming
+    let moves_blocked = look_cursor.position.is_some() || target_selection.pending.is_some();
+    let step = if moves_blocked {
+        None
+    } else if let Some(delta) = bindings.just_pressed_direction(&keyboard) {
+        Some(PlayerAction::Move(delta))
+    } else {
+        pressed(KeyAction::Wait).then_some(PlayerAction::Wait)
+    };
+
+    if let Some(action) = step {
+        inputs.send(PlayerInput(action));
+        *repeat = KeyRepeat {
+            held: KeyAction::MOVES
+                .into_iter()
+                .chain([KeyAction::Wait])
+                .filter(|action| pressed(*action))
+                .collect(),
+            action: Some(action),
+            timer: Timer::from_seconds(REPEAT_DELAY, TimerMode::Once),
+        };
+    } else if let Some(action) = repeat.action {
+        let held = repeat
+            .held
+            .iter()
+            .all(|held| bindings.pressed(&keyboard, *held));
+        if !held || moves_blocked {
+            repeat.action = None;
+        } else if repeat.timer.tick(time.delta()).finished() {
+            inputs.send(PlayerInput(action));
+            repeat.timer = Timer::from_seconds(REPEAT_INTERVAL, TimerMode::Once);
+        }
+    }
+
+    if pressed(KeyAction::PickUp) {
+        inputs.send(PlayerInput(PlayerAction::PickUp));
+    }
+    if pressed(KeyAction::Descend) {
+        inputs.send(PlayerInput(PlayerAction::Descend));
+    }
+
+    //only one of exploring, travelling and resting goes on at a time
+    if pressed(KeyAction::AutoExplore) {
+        let active = auto_explore.active;
+        travel.stop();
+        rest.stop();
+        if active {
+            auto_explore.stop();
+        } else {
+            auto_explore.start();
+        }
+    }
+    if pressed(KeyAction::Rest) {
+        auto_explore.stop();
+        travel.stop();
+        rest.start();
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use bevy::prelude::*;
 
@@ -7,6 +9,7 @@ use rand::distributions::Distribution;
 use rand::distributions::Standard;
 use rand::seq::IteratorRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::level_generation::generators::*;
 
@@ -29,7 +32,7 @@ impl Distribution<CardinalDirection> for Standard {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum ViewStatus {
     Seen,
     Revealed,
@@ -41,11 +44,25 @@ fn distance(p0: (usize, usize), p1: (usize, usize)) -> usize {
     std::cmp::max(p0.0.abs_diff(p1.0), p0.1.abs_diff(p1.1))
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Tile {
     pub sprite_index: usize,
     pub passable: bool,
     pub view_status: ViewStatus,
+    //leads down to the next level
+    pub stairs: bool,
+}
+
+impl Tile {
+    pub fn name(&self) -> &'static str {
+        if self.stairs {
+            "staircase down"
+        } else if self.passable {
+            "cave floor"
+        } else {
+            "cave wall"
+        }
+    }
 }
 
 impl Default for Tile {
@@ -54,17 +71,73 @@ impl Default for Tile {
             sprite_index: 206,
             passable: false,
             view_status: ViewStatus::Unexplored,
+            stairs: false,
         }
     }
 }
 
-#[derive(Resource)]
+//Side length, in tiles, of the square chunks the map is rendered in
+pub const CHUNK_SIZE: usize = 32;
+
+//Shared between all maps so that a freshly generated map never reuses an old map's version
+static NEXT_LAYOUT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+//Rows are mostly long runs of identical tiles, so they're saved as (tile, run length) pairs
+mod run_length {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Tile;
+
+    pub fn serialize<S: Serializer>(grid: &[Vec<Tile>], serializer: S) -> Result<S::Ok, S::Error> {
+        let rows: Vec<Vec<(&Tile, usize)>> = grid
+            .iter()
+            .map(|row| {
+                let mut runs: Vec<(&Tile, usize)> = Vec::new();
+                for tile in row {
+                    match runs.last_mut() {
+                        Some((run_tile, length)) if *run_tile == tile => *length += 1,
+                        _ => runs.push((tile, 1)),
+                    }
+                }
+                runs
+            })
+            .collect();
+        rows.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<Tile>>, D::Error> {
+        let rows: Vec<Vec<(Tile, usize)>> = Vec::deserialize(deserializer)?;
+        Ok(rows
+            .into_iter()
+            .map(|runs| {
+                runs.into_iter()
+                    .flat_map(|(tile, length)| std::iter::repeat_n(tile, length))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone)]
 pub struct Map {
+    #[serde(with = "run_length")]
     grid: Vec<Vec<Tile>>,
+    //chunks with tiles that may have changed since they were last rendered
+    #[serde(skip)]
+    dirty_chunks: HashSet<(usize, usize)>,
+    //changes whenever tiles may have become passable or impassable, so cached fov can be invalidated
+    #[serde(skip)]
+    layout_version: u64,
     pub width: usize,
     pub height: usize,
     pub player_spawn_points: Vec<(usize, usize)>,
     pub enemy_spawn_points: Vec<(usize, usize)>,
+    pub item_spawn_points: Vec<(usize, usize)>,
+    //wall tiles next to a cavern floor, for torches
+    pub torch_spawn_points: Vec<(usize, usize)>,
+    pub fungus_spawn_points: Vec<(usize, usize)>,
 }
 
 #[derive(Resource)]
@@ -81,15 +154,28 @@ impl Map {
     const HEIGHT: usize = 250;
     const WIDTH: usize = 500;
 
-    pub fn new(settings: MapGeneratorSettings) -> Self {
+    pub fn new<R: Rng>(settings: MapGeneratorSettings, rng: &mut R) -> Self {
+        let mut map = Self::filled(Self::WIDTH, Self::HEIGHT);
+        map.generate(settings, rng);
+        map
+    }
+
+    //A map of the given size made entirely of walls, without running any generator
+    pub fn filled(width: usize, height: usize) -> Self {
         let mut map = Map {
-            grid: vec![vec![Tile::default(); Self::WIDTH]; Self::HEIGHT],
-            width: Self::WIDTH,
-            height: Self::HEIGHT,
+            grid: vec![vec![Tile::default(); width]; height],
+            dirty_chunks: HashSet::new(),
+            layout_version: 0,
+            width,
+            height,
             player_spawn_points: Vec::new(),
             enemy_spawn_points: Vec::new(),
+            item_spawn_points: Vec::new(),
+            torch_spawn_points: Vec::new(),
+            fungus_spawn_points: Vec::new(),
         };
-        map.generate(settings);
+        map.mark_all_dirty();
+        map.mark_layout_changed();
         map
     }
 
@@ -99,6 +185,53 @@ impl Map {
                 *tile = Tile::default();
             })
         });
+        self.mark_all_dirty();
+        self.mark_layout_changed();
+    }
+
+    //Must be called on a map that was just deserialized, so it gets rendered and has its own layout version
+    pub fn mark_loaded(&mut self) {
+        self.mark_all_dirty();
+        self.mark_layout_changed();
+    }
+
+    pub fn layout_version(&self) -> u64 {
+        self.layout_version
+    }
+
+    //Must be called after changing whether tiles are passable outside of map generation
+    pub fn mark_layout_changed(&mut self) {
+        self.layout_version = NEXT_LAYOUT_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    //number of chunks along each axis
+    pub fn chunk_count(&self) -> (usize, usize) {
+        (
+            self.width.div_ceil(CHUNK_SIZE),
+            self.height.div_ceil(CHUNK_SIZE),
+        )
+    }
+
+    fn mark_all_dirty(&mut self) {
+        let (chunks_x, chunks_y) = self.chunk_count();
+        for x in 0..chunks_x {
+            for y in 0..chunks_y {
+                self.dirty_chunks.insert((x, y));
+            }
+        }
+    }
+
+    //Re-render the tile's chunk without otherwise changing it, e.g. when its lighting changes
+    pub fn mark_tile_dirty(&mut self, x: usize, y: usize) {
+        self.dirty_chunks.insert((x / CHUNK_SIZE, y / CHUNK_SIZE));
+    }
+
+    pub fn has_dirty_chunks(&self) -> bool {
+        !self.dirty_chunks.is_empty()
+    }
+
+    pub fn take_dirty_chunks(&mut self) -> HashSet<(usize, usize)> {
+        std::mem::take(&mut self.dirty_chunks)
     }
 
     //A* search
@@ -187,14 +320,14 @@ impl Map {
         None
     }
 
-    fn generate_connecting_tunnel(
+    fn generate_connecting_tunnel<R: Rng>(
         &mut self,
         start: (usize, usize),
         target: (usize, usize),
+        rng: &mut R,
     ) -> Vec<(usize, usize)> {
         let (mut x, mut y) = start;
         let mut path = Vec::new();
-        let mut rng = rand::thread_rng();
 
         for i in 0.. {
             use CardinalDirection::*;
@@ -246,10 +379,15 @@ impl Map {
         path
     }
 
-    fn random_walk(&mut self, x0: usize, y0: usize, walk_len: usize) -> Vec<(usize, usize)> {
+    fn random_walk<R: Rng>(
+        &mut self,
+        x0: usize,
+        y0: usize,
+        walk_len: usize,
+        rng: &mut R,
+    ) -> Vec<(usize, usize)> {
         let (mut x, mut y) = (x0, y0);
         let mut path = Vec::new();
-        let mut rng = rand::thread_rng();
 
         for _ in 0..walk_len {
             use CardinalDirection::*;
@@ -271,14 +409,15 @@ impl Map {
         path
     }
 
-    pub fn generate(&mut self, settings: MapGeneratorSettings) {
+    pub fn generate<R: Rng>(&mut self, settings: MapGeneratorSettings, rng: &mut R) {
         use MapGeneratorSettings::*;
         match settings {
-            Cavern(settings) => self.generate_caverns(settings),
+            Cavern(settings) => self.generate_caverns(settings, rng),
         }
+        self.mark_layout_changed();
     }
 
-    pub fn generate_caverns(&mut self, settings: CavernSettings) {
+    pub fn generate_caverns<R: Rng>(&mut self, settings: CavernSettings, rng: &mut R) {
         let CavernSettings {
             cavern_count,
             max_cavern_dist,
@@ -286,7 +425,6 @@ impl Map {
             walk_len,
         } = settings;
         let mut caverns = vec![(self.width / 2, self.height / 2)];
-        let mut rng = rand::thread_rng();
 
         //randomly select cavern locations, within a certain distance from one another
         while caverns.len() < cavern_count {
@@ -304,7 +442,7 @@ impl Map {
         for (x0, y0) in &caverns {
             let mut points = BTreeSet::new();
             for _ in 0..walk_count {
-                for point in self.random_walk(*x0, *y0, walk_len) {
+                for point in self.random_walk(*x0, *y0, walk_len, rng) {
                     points.insert(point);
                 }
             }
@@ -320,18 +458,29 @@ impl Map {
                     .filter(|other_cavern| self.get_path(*cavern, **other_cavern).is_none())
                     .min_by_key(|other_cavern| distance(*cavern, **other_cavern));
 
-                self.generate_connecting_tunnel(*cavern, *closest_unconnected.unwrap());
+                self.generate_connecting_tunnel(*cavern, *closest_unconnected.unwrap(), rng);
             }
         });
 
         //Set player spawn point
         self.player_spawn_points.push(origin);
 
+        //Put the stairs down as far from the player as possible
+        if let Some(&(x, y)) = cavern_points
+            .iter()
+            .flatten()
+            .max_by_key(|point| distance(origin, **point))
+        {
+            let tile = self.get_mut(x, y).unwrap();
+            tile.stairs = true;
+            tile.sprite_index = 127;
+        }
+
         for points in cavern_points {
             let spawn_attempts = rng.gen_range(0..5);
             points
                 .iter()
-                .choose_multiple(&mut rng, spawn_attempts)
+                .choose_multiple(rng, spawn_attempts)
                 .iter()
                 .for_each(|point| {
                     if !self.player_spawn_points.contains(point)
@@ -339,8 +488,77 @@ impl Map {
                     {
                         self.enemy_spawn_points.push(**point);
                     }
+                });
+
+            let item_attempts = rng.gen_range(0..4);
+            points
+                .iter()
+                .choose_multiple(rng, item_attempts)
+                .iter()
+                .for_each(|point| {
+                    if !self.player_spawn_points.contains(point)
+                        && !self.enemy_spawn_points.contains(point)
+                        && !self.item_spawn_points.contains(point)
+                    {
+                        self.item_spawn_points.push(**point);
+                    }
+                });
+
+            //torches go on the walls around the cavern's edge
+            let edge_walls: BTreeSet<(usize, usize)> = points
+                .iter()
+                .flat_map(|(x, y)| {
+                    [
+                        (*x + 1, *y),
+                        (x.wrapping_sub(1), *y),
+                        (*x, *y + 1),
+                        (*x, y.wrapping_sub(1)),
+                    ]
                 })
+                .filter(|(x, y)| self.get(*x, *y).is_some_and(|tile| !tile.passable))
+                .collect();
+            let torch_count = edge_walls.len() / 40;
+            self.torch_spawn_points
+                .extend(edge_walls.into_iter().choose_multiple(rng, torch_count));
+
+            let fungus_attempts = points.len() / 200;
+            points
+                .iter()
+                .choose_multiple(rng, fungus_attempts)
+                .iter()
+                .for_each(|point| {
+                    if !self.player_spawn_points.contains(point)
+                        && !self.item_spawn_points.contains(point)
+                        && !self.fungus_spawn_points.contains(point)
+                    {
+                        self.fungus_spawn_points.push(**point);
+                    }
+                });
+        }
+    }
+
+    //Bresenham line from start to end, not including start
+    pub fn line(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+        let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+        let (x1, y1) = (end.0 as isize, end.1 as isize);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut error = dx + dy;
+        let mut points = Vec::new();
+
+        while (x, y) != (x1, y1) {
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += sx;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += sy;
+            }
+            points.push((x as usize, y as usize));
         }
+        points
     }
 
     pub fn get(&self, x: usize, y: usize) -> Option<&Tile> {
@@ -351,9 +569,14 @@ impl Map {
         }
     }
 
+    //Marks the tile's chunk for re-rendering
     pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Tile> {
         if let Some(row) = self.grid.get_mut(y) {
-            row.get_mut(x)
+            let tile = row.get_mut(x);
+            if tile.is_some() {
+                self.dirty_chunks.insert((x / CHUNK_SIZE, y / CHUNK_SIZE));
+            }
+            tile
         } else {
             None
         }
@@ -362,6 +585,6 @@ impl Map {
 
 impl Default for Map {
     fn default() -> Self {
-        Self::new(MapGeneratorSettings::default())
+        Self::new(MapGeneratorSettings::default(), &mut rand::thread_rng())
     }
 }
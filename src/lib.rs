@@ -1,15 +1,98 @@
-use bevy::prelude::*;
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_egui::EguiPlugin;
+use position::Position;
 
 pub mod actor;
+pub mod animation;
+pub mod auto_explore;
+pub mod bot;
 pub mod camera_controls;
+pub mod combat;
+pub mod effect;
 pub mod fov;
+pub mod game_state;
+pub mod gamepad_controls;
+pub mod item;
+pub mod key_bindings;
 pub mod level_generation;
+pub mod lighting;
+pub mod look;
+pub mod map_view;
+pub mod menu;
+pub mod message_log;
+pub mod minimap;
+pub mod pathfinding;
+pub mod player_action;
 pub mod position;
+pub mod replay;
+pub mod rest;
+pub mod rng;
+pub mod save;
 pub mod sprite_atlas;
+pub mod status;
+pub mod storage;
+pub mod targeting;
+pub mod touch_controls;
+pub mod travel;
+pub mod travel_view;
+pub mod ui;
+pub mod view;
 
-pub fn world_to_map_index(transform: &Transform) -> (usize, usize) {
-    (
-        transform.translation.x as usize / 12,
-        transform.translation.y as usize / 12,
-    )
+pub fn world_to_map_position(world_position: Vec2) -> Option<Position> {
+    let (x, y) = (
+        (world_position.x / 12.0).round(),
+        (world_position.y / 12.0).round(),
+    );
+    (x >= 0.0 && y >= 0.0).then(|| Position::new(x as usize, y as usize))
+}
+
+//The game itself: map, actors, turns, combat, fov, saving and replays. Needs nothing drawn,
+//so it runs under MinimalPlugins as well as DefaultPlugins.
+pub struct SimulationPlugins;
+
+impl PluginGroup for SimulationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(game_state::GameStatePlugin)
+            .add(level_generation::MapPlugin)
+            .add(fov::FovPlugin)
+            .add(lighting::LightingPlugin)
+            .add(actor::ActorPlugin)
+            .add(item::ItemPlugin)
+            .add(effect::EffectPlugin)
+            .add(combat::CombatPlugin)
+            .add(status::StatusPlugin)
+            .add(message_log::MessageLogPlugin)
+            .add(player_action::PlayerActionPlugin)
+            .add(auto_explore::AutoExplorePlugin)
+            .add(travel::TravelPlugin)
+            .add(rest::RestPlugin)
+            .add(save::SavePlugin)
+            .add(replay::ReplayPlugin)
+    }
+}
+
+//Everything that draws the simulation or lets the player interact with it, on top of
+//SimulationPlugins and DefaultPlugins
+pub struct ViewPlugins;
+
+impl PluginGroup for ViewPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(EguiPlugin)
+            .add(sprite_atlas::SpriteAtlasPlugin)
+            .add(key_bindings::KeyBindingsPlugin)
+            .add(gamepad_controls::GamepadControlsPlugin)
+            .add(touch_controls::TouchControlsPlugin)
+            .add(camera_controls::CameraControlsPlugin)
+            .add(map_view::MapViewPlugin)
+            .add(view::ViewPlugin)
+            .add(animation::AnimationPlugin)
+            .add(targeting::TargetingPlugin)
+            .add(travel_view::TravelViewPlugin)
+            .add(look::LookPlugin)
+            .add(minimap::MinimapPlugin)
+            .add(menu::MenuPlugin)
+            .add(ui::UiPlugin)
+    }
 }
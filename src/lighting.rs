@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    actor::TurnState,
+    fov::{compute_fov, FovAlgorithm},
+    game_state::{AppState, GameplaySet, LevelSet},
+    level_generation::map::Map,
+    position::Position,
+    sprite_atlas::{Renderable, SpriteIndex},
+    status::{StatusEffects, StatusKind},
+};
+
+//Light every tile gets regardless of light sources, so dim tiles stay readable
+const AMBIENT_LIGHT: f32 = 0.15;
+//Tiles darker than this can't be seen, even when they're in view
+const MIN_VISIBLE_LIGHT: f32 = 0.05;
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightMap>()
+            .add_systems(
+                OnEnter(AppState::LoadingLevel),
+                spawn_light_sources.in_set(LevelSet::Spawn),
+            )
+            .add_systems(OnEnter(TurnState::Player), burn_fires)
+            .add_systems(Update, update_light_map.in_set(GameplaySet));
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct LightSource {
+    pub radius: usize,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl LightSource {
+    pub const LANTERN: LightSource = LightSource {
+        radius: 6,
+        color: Color::rgb(1.0, 0.85, 0.6),
+        intensity: 1.0,
+    };
+    pub const TORCH: LightSource = LightSource {
+        radius: 7,
+        color: Color::rgb(1.0, 0.6, 0.3),
+        intensity: 1.0,
+    };
+    pub const FUNGUS: LightSource = LightSource {
+        radius: 3,
+        color: Color::rgb(0.4, 0.9, 0.8),
+        intensity: 0.7,
+    };
+    //given off by anything that is burning
+    pub const FIRE: LightSource = LightSource {
+        radius: 4,
+        color: Color::rgb(1.0, 0.45, 0.15),
+        intensity: 1.0,
+    };
+}
+
+//Turns anything standing in a fire burns for
+const FIRE_BURN_TURNS: u32 = 2;
+
+//A burning tile. It has no sprite of its own, it shows up through the light it gives off.
+#[derive(Component)]
+pub struct Fire {
+    pub turns_left: u32,
+}
+
+pub fn fire_bundle(position: Position, turns: u32) -> impl Bundle {
+    (
+        Fire { turns_left: turns },
+        LightSource::FIRE,
+        Name::new("fire"),
+        position,
+    )
+}
+
+//Light reaching each tile from every light source. Tiles missing from the map are dark.
+#[derive(Resource, Default)]
+pub struct LightMap {
+    light: HashMap<(usize, usize), Vec3>,
+    //what was giving off light, and the layout version of the map, when the light was calculated
+    sources: Vec<(Entity, Position, LightSource)>,
+    layout_version: Option<u64>,
+}
+
+impl LightMap {
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.light.get(&(x, y)).copied().unwrap_or(Vec3::ZERO)
+    }
+
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).max_element() >= MIN_VISIBLE_LIGHT
+    }
+
+    //colour to tint a visible tile, or anything standing on it, with
+    pub fn tint(&self, x: usize, y: usize) -> Color {
+        let light = (Vec3::splat(AMBIENT_LIGHT) + self.get(x, y)).min(Vec3::ONE);
+        Color::rgb(light.x, light.y, light.z)
+    }
+}
+
+pub fn spawn_light_sources(mut commands: Commands, map: Res<Map>) {
+    let torches = map
+        .torch_spawn_points
+        .iter()
+        .map(|point| (point, "torch", SpriteIndex::Torch, LightSource::TORCH));
+    let fungi = map.fungus_spawn_points.iter().map(|point| {
+        (
+            point,
+            "glowing fungus",
+            SpriteIndex::Fungus,
+            LightSource::FUNGUS,
+        )
+    });
+
+    for (point, name, sprite_index, light_source) in torches.chain(fungi) {
+        commands.spawn((
+            Renderable {
+                sprite_index: sprite_index as usize,
+                layer: 0.25,
+            },
+            light_source,
+            Name::new(name),
+            Position::new(point.0, point.1),
+        ));
+    }
+}
+
+//Sets whatever is standing in a fire alight, and burns the fire down by a turn
+fn burn_fires(
+    mut commands: Commands,
+    mut fires: Query<(Entity, &mut Fire, &Position)>,
+    mut actors: Query<(&Position, &mut StatusEffects)>,
+) {
+    for (entity, mut fire, fire_position) in fires.iter_mut() {
+        for (position, mut status_effects) in actors.iter_mut() {
+            if position == fire_position {
+                status_effects.apply(StatusKind::Burning, FIRE_BURN_TURNS);
+            }
+        }
+        fire.turns_left = fire.turns_left.saturating_sub(1);
+        if fire.turns_left == 0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+//Anything burning gives off firelight, on top of any light it carries
+pub fn update_light_map(
+    mut map: ResMut<Map>,
+    mut light_map: ResMut<LightMap>,
+    emitters: Query<(
+        Entity,
+        &Position,
+        Option<&LightSource>,
+        Option<&StatusEffects>,
+    )>,
+) {
+    //status effects tick every turn, so compare the lights themselves rather than relying on
+    //change detection
+    let mut sources: Vec<(Entity, Position, LightSource)> = emitters
+        .iter()
+        .flat_map(|(entity, position, light_source, status_effects)| {
+            let fire = status_effects
+                .is_some_and(|status_effects| status_effects.has(StatusKind::Burning))
+                .then_some(LightSource::FIRE);
+            light_source
+                .copied()
+                .into_iter()
+                .chain(fire)
+                .map(move |source| (entity, *position, source))
+        })
+        .collect();
+    sources.sort_by_key(|(entity, _, _)| *entity);
+    if sources == light_map.sources && light_map.layout_version == Some(map.layout_version()) {
+        return;
+    }
+
+    let mut light: HashMap<(usize, usize), Vec3> = HashMap::new();
+    for (_, position, source) in &sources {
+        let color = Vec3::new(source.color.r(), source.color.g(), source.color.b());
+        let origin = (position.x, position.y);
+        for (x, y) in compute_fov(
+            &map,
+            origin,
+            source.radius,
+            FovAlgorithm::SymmetricShadowcasting,
+        ) {
+            let (dx, dy) = (x.abs_diff(origin.0) as f32, y.abs_diff(origin.1) as f32);
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt() / (source.radius as f32 + 1.0);
+            *light.entry((x, y)).or_default() += color * source.intensity * falloff;
+        }
+    }
+
+    let changed_tiles: Vec<(usize, usize)> = light
+        .keys()
+        .chain(light_map.light.keys())
+        .filter(|tile| light.get(tile) != light_map.light.get(tile))
+        .copied()
+        .collect();
+    for (x, y) in changed_tiles {
+        map.mark_tile_dirty(x, y);
+    }
+
+    light_map.light = light;
+    light_map.sources = sources;
+    light_map.layout_version = Some(map.layout_version());
+}
@@ -0,0 +1,244 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{Actor, CombatStats, Enemy, Player},
+    camera_controls::{cursor_world_position, MainCamera},
+    fov::{Ghost, Spotted},
+    game_state::{AppState, GameplaySet},
+    item::{Item, ItemDefinitions},
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::{Map, ViewStatus},
+    lighting::LightSource,
+    position::Position,
+    status::StatusEffects,
+    targeting::TargetSelection,
+    world_to_map_position,
+};
+
+pub struct LookPlugin;
+
+impl Plugin for LookPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LookCursor>()
+            .add_systems(OnEnter(AppState::NewGame), reset_look_cursor)
+            .add_systems(OnEnter(AppState::LoadingSave), reset_look_cursor)
+            .add_systems(
+                Update,
+                (toggle_look_mode, move_look_cursor, look)
+                    .chain()
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Set while the player is moving the look cursor around with the movement keys
+#[derive(Resource, Default)]
+pub struct LookCursor {
+    pub position: Option<Position>,
+}
+
+//A new or loaded run starts out not looking at anything
+fn reset_look_cursor(mut cursor: ResMut<LookCursor>) {
+    *cursor = LookCursor::default();
+}
+
+fn toggle_look_mode(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    player_query: Query<&Position, With<Player>>,
+    mut cursor: ResMut<LookCursor>,
+) {
+    if bindings.just_pressed(&keyboard, KeyAction::Cancel) {
+        cursor.position = None;
+    } else if bindings.just_pressed(&keyboard, KeyAction::Look) {
+        cursor.position = match cursor.position {
+            Some(_) => None,
+            None => player_query.get_single().ok().copied(),
+        };
+    }
+}
+
+//Moved with the same keys as the player
+fn move_look_cursor(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    map: Res<Map>,
+    mut cursor: ResMut<LookCursor>,
+) {
+    let (Some(position), Some(delta)) =
+        (cursor.position, bindings.just_pressed_direction(&keyboard))
+    else {
+        return;
+    };
+    let moved = position + delta;
+    if map.get(moved.x, moved.y).is_some() {
+        cursor.position = Some(moved);
+    }
+}
+
+//Details of a visible enemy shown when looking at it
+struct Examined {
+    name: String,
+    health: f32,
+    max_health: f32,
+    stats: CombatStats,
+    statuses: Vec<String>,
+}
+
+//What the player knows about a tile: what they can see on it now, or remember from before
+#[derive(Default)]
+struct TileDescription {
+    lines: Vec<String>,
+    examined: Vec<Examined>,
+}
+
+fn show_description(ui: &mut egui::Ui, description: &TileDescription) {
+    for line in &description.lines {
+        ui.label(line);
+    }
+    for examined in &description.examined {
+        ui.separator();
+        ui.strong(&examined.name);
+        let health = (examined.health / examined.max_health).clamp(0.0, 1.0);
+        ui.add(
+            egui::ProgressBar::new(health)
+                .desired_width(160.0)
+                .fill(egui::Color32::DARK_RED)
+                .text(format!(
+                    "HP {:.0}/{:.0}",
+                    examined.health.max(0.0),
+                    examined.max_health
+                )),
+        );
+        ui.label(format!(
+            "Attack: {:.0}   Defense: {:.0}",
+            examined.stats.attack, examined.stats.defense
+        ));
+        if !examined.statuses.is_empty() {
+            ui.label(examined.statuses.join(", "));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn look(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    cursor: Res<LookCursor>,
+    bindings: Res<KeyBindings>,
+    target_selection: Res<TargetSelection>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Res<Map>,
+    item_definitions: Res<ItemDefinitions>,
+    player_query: Query<&Position, With<Player>>,
+    enemy_query: Query<(&Position, &Name, &Actor, &CombatStats, &StatusEffects), With<Enemy>>,
+    ghost_query: Query<(&Position, &Name), With<Ghost>>,
+    item_query: Query<(&Position, &Item), With<Spotted>>,
+    feature_query: Query<(&Position, &Name), (With<LightSource>, Without<Player>)>,
+) {
+    let ctx = contexts.ctx_mut();
+    let target = match cursor.position {
+        Some(position) => position,
+        None => {
+            if target_selection.pending.is_some() || ctx.is_pointer_over_area() {
+                return;
+            }
+            let Ok(window) = window_query.get_single() else {
+                return;
+            };
+            let (camera, camera_transform) = camera_query.single();
+            let Some(hovered) = cursor_world_position(window, camera, camera_transform)
+                .and_then(world_to_map_position)
+            else {
+                return;
+            };
+            hovered
+        }
+    };
+
+    let description = map
+        .get(target.x, target.y)
+        .filter(|tile| tile.view_status != ViewStatus::Unexplored)
+        .map(|tile| {
+            let seen = tile.view_status == ViewStatus::Seen;
+            let here = |position: &Position| *position == target;
+            let mut description = TileDescription::default();
+
+            if player_query.iter().any(here) {
+                description.lines.push("You are standing here.".into());
+            }
+            if seen {
+                for (_, name, actor, stats, status_effects) in
+                    enemy_query.iter().filter(|(position, ..)| here(position))
+                {
+                    description.lines.push(format!("A {name}."));
+                    description.examined.push(Examined {
+                        name: name.to_string(),
+                        health: actor.health,
+                        max_health: actor.max_health,
+                        stats: *stats,
+                        statuses: status_effects
+                            .effects
+                            .iter()
+                            .map(|effect| {
+                                format!("{} ({})", effect.kind.adjective(), effect.turns_left)
+                            })
+                            .collect(),
+                    });
+                }
+            } else {
+                for (_, name) in ghost_query.iter().filter(|(position, _)| here(position)) {
+                    description
+                        .lines
+                        .push(format!("You last saw a {name} here."));
+                }
+            }
+            for (_, name) in feature_query.iter().filter(|(position, _)| here(position)) {
+                description.lines.push(format!("A {name}."));
+            }
+            for (_, item) in item_query.iter().filter(|(position, _)| here(position)) {
+                let item_description = item_definitions[item.kind].describe(item.count);
+                description
+                    .lines
+                    .push(format!("You see {item_description}."));
+            }
+
+            let remembered = if seen { "" } else { " (remembered)" };
+            description
+                .lines
+                .push(format!("{}{remembered}", tile.name()));
+            description
+        });
+
+    if cursor.position.is_some() {
+        gizmos.rect_2d(
+            Vec2::new(target.x as f32, target.y as f32) * 12.0,
+            0.0,
+            Vec2::splat(12.0),
+            Color::YELLOW,
+        );
+        egui::Window::new("Look")
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+            .resizable(false)
+            .show(ctx, |ui| {
+                match &description {
+                    Some(description) => show_description(ui, description),
+                    None => {
+                        ui.label("You haven't explored there.");
+                    }
+                }
+                ui.separator();
+                ui.small(format!(
+                    "{}: move   {}: stop looking",
+                    bindings.describe(&KeyAction::MOVES),
+                    bindings.describe(&[KeyAction::Look, KeyAction::Cancel])
+                ));
+            });
+    } else if let Some(description) = description {
+        egui::show_tooltip_at_pointer(ctx, egui::Id::new("look_tooltip"), |ui| {
+            show_description(ui, &description)
+        });
+    }
+}
@@ -1,11 +1,5 @@
 use bevy::prelude::*;
-use roguelike::{
-    actor::ActorPlugin,
-    camera_controls::{CameraControlsPlugin, MainCamera},
-    fov::FovPlugin,
-    level_generation::{generators::MapGeneratorSettings, map::Map, MapPlugin},
-    sprite_atlas::SpriteAtlasPlugin,
-};
+use roguelike::{camera_controls::MainCamera, SimulationPlugins, ViewPlugins};
 
 fn main() {
     App::new()
@@ -20,13 +14,7 @@ fn main() {
                     ..Default::default()
                 }),
         )
-        .add_plugins((
-            SpriteAtlasPlugin,
-            CameraControlsPlugin,
-            FovPlugin,
-            MapPlugin,
-            ActorPlugin,
-        ))
+        .add_plugins((SimulationPlugins, ViewPlugins))
         .insert_resource(ClearColor(Color::BLACK))
         .add_systems(Startup, setup)
         .run();
@@ -34,10 +22,4 @@ fn main() {
 
 fn setup(mut commands: Commands) {
     commands.spawn((Camera2dBundle::default(), MainCamera));
-
-    let map_settings = MapGeneratorSettings::default();
-    let map = Map::new(map_settings);
-    eprintln!("Map Created!");
-    commands.insert_resource(map_settings);
-    commands.insert_resource(map);
 }
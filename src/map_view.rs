@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{
+    level_generation::map::{Map, ViewStatus, CHUNK_SIZE},
+    lighting::LightMap,
+    sprite_atlas::SpriteAtlas,
+};
+
+//One mesh covering a CHUNK_SIZE x CHUNK_SIZE block of tiles, identified by its chunk coordinates
+#[derive(Component)]
+pub struct MapChunk {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Resource)]
+pub struct MapRenderSettings {
+    //colour tiles by their view status, otherwise draw everything fully lit
+    pub fog_of_war: bool,
+}
+
+impl Default for MapRenderSettings {
+    fn default() -> Self {
+        Self { fog_of_war: true }
+    }
+}
+
+pub struct MapViewPlugin;
+
+impl Plugin for MapViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapRenderSettings>().add_systems(
+            PostUpdate,
+            (
+                update_map_chunks.run_if(resource_exists::<Map>()),
+                despawn_map_chunks.run_if(resource_removed::<Map>()),
+            ),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_map_chunks(
+    mut commands: Commands,
+    mut map: ResMut<Map>,
+    atlas: Res<SpriteAtlas>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<MapRenderSettings>,
+    light_map: Option<Res<LightMap>>,
+    chunk_query: Query<(&MapChunk, &Mesh2dHandle)>,
+    mut material: Local<Option<Handle<ColorMaterial>>>,
+) {
+    if !map.has_dirty_chunks() && !settings.is_changed() {
+        return;
+    }
+    let Some(texture_atlas) = texture_atlases.get(&atlas.handle) else {
+        return;
+    };
+    let material = material
+        .get_or_insert_with(|| {
+            materials.add(ColorMaterial {
+                color: Color::WHITE,
+                texture: Some(texture_atlas.texture.clone()),
+            })
+        })
+        .clone();
+
+    let existing_chunks: HashMap<(usize, usize), Handle<Mesh>> = chunk_query
+        .iter()
+        .map(|(chunk, mesh)| ((chunk.x, chunk.y), mesh.0.clone()))
+        .collect();
+
+    let mut dirty_chunks = map.take_dirty_chunks();
+    if settings.is_changed() {
+        dirty_chunks.extend(existing_chunks.keys().copied());
+    }
+
+    for (x, y) in dirty_chunks {
+        let mesh = build_chunk_mesh(
+            &map,
+            light_map.as_deref(),
+            (x, y),
+            texture_atlas,
+            settings.fog_of_war,
+        );
+        if let Some(existing_mesh) = existing_chunks
+            .get(&(x, y))
+            .and_then(|handle| meshes.get_mut(handle))
+        {
+            *existing_mesh = mesh;
+        } else {
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(mesh).into(),
+                    material: material.clone(),
+                    ..Default::default()
+                },
+                MapChunk { x, y },
+            ));
+        }
+    }
+}
+
+fn despawn_map_chunks(mut commands: Commands, chunk_query: Query<Entity, With<MapChunk>>) {
+    for entity in chunk_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn build_chunk_mesh(
+    map: &Map,
+    light_map: Option<&LightMap>,
+    chunk: (usize, usize),
+    texture_atlas: &TextureAtlas,
+    fog_of_war: bool,
+) -> Mesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    for x in chunk.0 * CHUNK_SIZE..(chunk.0 + 1) * CHUNK_SIZE {
+        for y in chunk.1 * CHUNK_SIZE..(chunk.1 + 1) * CHUNK_SIZE {
+            let Some(tile) = map.get(x, y) else {
+                continue;
+            };
+            let color = match (fog_of_war, &tile.view_status) {
+                (false, _) => Color::WHITE,
+                (true, ViewStatus::Seen) => {
+                    light_map.map_or(Color::WHITE, |light_map| light_map.tint(x, y))
+                }
+                (true, ViewStatus::Revealed) => Color::GRAY,
+                (true, ViewStatus::Unexplored) => Color::BLACK,
+            };
+
+            //tiles are centred on their map position, like the sprites drawn on top of them
+            let (left, bottom) = (x as f32 * 12.0 - 6.0, y as f32 * 12.0 - 6.0);
+            let rect = texture_atlas.textures[tile.sprite_index];
+            let (uv_min, uv_max) = (rect.min / texture_atlas.size, rect.max / texture_atlas.size);
+
+            let first_index = positions.len() as u32;
+            positions.extend([
+                [left, bottom, 0.0],
+                [left + 12.0, bottom, 0.0],
+                [left + 12.0, bottom + 12.0, 0.0],
+                [left, bottom + 12.0, 0.0],
+            ]);
+            uvs.extend([
+                [uv_min.x, uv_max.y],
+                [uv_max.x, uv_max.y],
+                [uv_max.x, uv_min.y],
+                [uv_min.x, uv_min.y],
+            ]);
+            colors.extend([color.as_linear_rgba_f32(); 4]);
+            indices.extend([0, 1, 2, 0, 2, 3].map(|offset| first_index + offset));
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{TurnCount, TurnState},
+    fov::FovAlgorithm,
+    game_state::AppState,
+    gamepad_controls::GamepadInput,
+    key_bindings::{KeyAction, KeyBindings, WriteKeyBindings},
+    level_generation::Depth,
+    replay::{read_replay, ReplayPlayback, ReplaySlot},
+    save::{SaveGame, SaveSlot},
+};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                toggle_pause,
+                main_menu.run_if(in_state(AppState::MainMenu)),
+                pause_menu.run_if(in_state(AppState::Paused)),
+                game_over_screen.run_if(in_state(AppState::GameOver)),
+                victory_screen.run_if(in_state(AppState::Victory)),
+            ),
+        );
+    }
+}
+
+fn toggle_pause(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    gamepad: GamepadInput,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let toggled = bindings.just_pressed(&keyboard, KeyAction::Pause)
+        || gamepad.just_pressed(GamepadButtonType::Start);
+    match state.get() {
+        AppState::Playing if toggled => next_state.set(AppState::Paused),
+        //the gamepad's back button resumes as well
+        AppState::Paused if toggled || gamepad.just_pressed(GamepadButtonType::East) => {
+            next_state.set(AppState::Playing)
+        }
+        _ => {}
+    }
+}
+
+fn menu_window(title: &str) -> egui::Window<'_> {
+    egui::Window::new(title)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .collapsible(false)
+        .resizable(false)
+}
+
+fn main_menu(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    slot: Res<SaveSlot>,
+    mut replay_slot: ResMut<ReplaySlot>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    menu_window("Roguelike").show(contexts.ctx_mut(), |ui| {
+        ui.vertical_centered(|ui| {
+            if ui
+                .add_enabled(slot.exists, egui::Button::new("Continue"))
+                .clicked()
+            {
+                next_state.set(AppState::LoadingSave);
+            }
+            if ui.button("New Game").clicked() {
+                next_state.set(AppState::NewGame);
+            }
+            if ui
+                .add_enabled(replay_slot.exists, egui::Button::new("Watch Replay"))
+                .clicked()
+            {
+                match read_replay() {
+                    Ok(replay) => {
+                        replay_slot.error = None;
+                        commands.insert_resource(ReplayPlayback::from(replay));
+                        next_state.set(AppState::NewGame);
+                    }
+                    Err(error) => {
+                        replay_slot.error = Some(format!("Couldn't watch the replay: {error}"));
+                    }
+                }
+            }
+            if ui.button("Quit").clicked() {
+                exit.send(bevy::app::AppExit);
+            }
+            for error in [&slot.error, &replay_slot.error].into_iter().flatten() {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+        });
+    });
+}
+
+//Saving is only allowed on the player's turn, see SaveGame
+fn pause_menu(
+    mut contexts: EguiContexts,
+    turn_state: Res<State<TurnState>>,
+    mut fov_algorithm: ResMut<FovAlgorithm>,
+    mut save_events: EventWriter<SaveGame>,
+    mut write_bindings_events: EventWriter<WriteKeyBindings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    menu_window("Paused").show(contexts.ctx_mut(), |ui| {
+        ui.vertical_centered(|ui| {
+            if ui.button("Resume").clicked() {
+                next_state.set(AppState::Playing);
+            }
+            if ui
+                .add_enabled(
+                    *turn_state.get() == TurnState::Player,
+                    egui::Button::new("Save Game"),
+                )
+                .clicked()
+            {
+                save_events.send(SaveGame);
+            }
+            if ui.button("Write Key Bindings File").clicked() {
+                write_bindings_events.send(WriteKeyBindings);
+            }
+            if ui.button("Main Menu").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+
+            //only written when picked, as any change recalculates the field of view
+            let mut algorithm = *fov_algorithm;
+            egui::ComboBox::from_label("Field of view")
+                .selected_text(algorithm.name())
+                .show_ui(ui, |ui| {
+                    for option in FovAlgorithm::ALL {
+                        ui.selectable_value(&mut algorithm, option, option.name());
+                    }
+                });
+            if algorithm != *fov_algorithm {
+                *fov_algorithm = algorithm;
+            }
+        });
+    });
+}
+
+fn end_screen(ui: &mut egui::Ui, text: String, next_state: &mut NextState<AppState>) {
+    ui.vertical_centered(|ui| {
+        ui.label(text);
+        if ui.button("New Game").clicked() {
+            next_state.set(AppState::NewGame);
+        }
+        if ui.button("Main Menu").clicked() {
+            next_state.set(AppState::MainMenu);
+        }
+    });
+}
+
+fn game_over_screen(
+    mut contexts: EguiContexts,
+    depth: Res<Depth>,
+    turn_count: Res<TurnCount>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    menu_window("You died").show(contexts.ctx_mut(), |ui| {
+        let text = format!(
+            "You died on depth {} after {} turns.",
+            depth.0, turn_count.0
+        );
+        end_screen(ui, text, &mut next_state);
+    });
+}
+
+fn victory_screen(
+    mut contexts: EguiContexts,
+    turn_count: Res<TurnCount>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    menu_window("Victory").show(contexts.ctx_mut(), |ui| {
+        let text = format!("You escaped the caves in {} turns!", turn_count.0);
+        end_screen(ui, text, &mut next_state);
+    });
+}
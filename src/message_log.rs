@@ -0,0 +1,46 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct MessageLogPlugin;
+
+impl Plugin for MessageLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MessageLog>();
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageKind {
+    Info,
+    Good,
+    Warning,
+    Danger,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Message {
+    pub text: String,
+    pub kind: MessageKind,
+}
+
+//Messages kept before the oldest start being dropped
+pub const MESSAGE_LOG_CAPACITY: usize = 200;
+
+#[derive(Resource, Default)]
+pub struct MessageLog {
+    pub messages: VecDeque<Message>,
+}
+
+impl MessageLog {
+    pub fn push(&mut self, kind: MessageKind, text: impl Into<String>) {
+        if self.messages.len() == MESSAGE_LOG_CAPACITY {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(Message {
+            text: text.into(),
+            kind,
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{Enemy, Player},
+    camera_controls::{pan_camera_to, CameraMode, MainCamera},
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::{Map, Tile, ViewStatus},
+    position::Position,
+};
+
+//Size of one map tile on the minimap, in screen pixels
+const MINIMAP_SCALE: f32 = 0.5;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Minimap>().add_systems(
+            Update,
+            (toggle_minimap, minimap)
+                .chain()
+                .run_if(resource_exists::<Map>()),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct Minimap {
+    pub open: bool,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self { open: true }
+    }
+}
+
+fn toggle_minimap(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut minimap: ResMut<Minimap>,
+) {
+    if bindings.just_pressed(&keyboard, KeyAction::Minimap) {
+        minimap.open = !minimap.open;
+    }
+}
+
+fn tile_color(tile: &Tile) -> egui::Color32 {
+    let brightness = match tile.view_status {
+        ViewStatus::Unexplored => return egui::Color32::BLACK,
+        ViewStatus::Revealed => 0.6,
+        ViewStatus::Seen => 1.0,
+    };
+    let (r, g, b) = if tile.stairs {
+        (255.0, 210.0, 60.0)
+    } else if tile.passable {
+        (70.0, 70.0, 80.0)
+    } else {
+        (170.0, 170.0, 170.0)
+    };
+    egui::Color32::from_rgb(
+        (r * brightness) as u8,
+        (g * brightness) as u8,
+        (b * brightness) as u8,
+    )
+}
+
+//One pixel per tile, with the top row of the image being the top of the map
+fn minimap_image(map: &Map) -> egui::ColorImage {
+    let mut pixels = Vec::with_capacity(map.width * map.height);
+    for y in (0..map.height).rev() {
+        for x in 0..map.width {
+            pixels.push(tile_color(map.get(x, y).unwrap()));
+        }
+    }
+    egui::ColorImage {
+        size: [map.width, map.height],
+        pixels,
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn minimap(
+    mut contexts: EguiContexts,
+    minimap: Res<Minimap>,
+    map: Res<Map>,
+    player_query: Query<&Position, With<Player>>,
+    enemy_query: Query<&Position, With<Enemy>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    mut mode: ResMut<CameraMode>,
+    mut texture: Local<Option<egui::TextureHandle>>,
+) {
+    if !minimap.open {
+        //rebuilt from scratch when reopened, since the map may have changed in the meantime
+        *texture = None;
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    match texture.as_mut() {
+        Some(texture) if map.is_changed() => {
+            texture.set(minimap_image(&map), egui::TextureOptions::NEAREST)
+        }
+        Some(_) => {}
+        None => {
+            *texture = Some(ctx.load_texture(
+                "minimap",
+                minimap_image(&map),
+                egui::TextureOptions::NEAREST,
+            ))
+        }
+    }
+    let texture_id = texture.as_ref().unwrap().id();
+    let Ok((mut camera_transform, projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    egui::Window::new("Minimap")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let size = egui::vec2(map.width as f32, map.height as f32) * MINIMAP_SCALE;
+            let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+            let rect = response.rect;
+
+            //map tiles are centred on whole numbers, minimap pixels start at their corners
+            let to_minimap = |x: f32, y: f32| {
+                rect.min + egui::vec2(x + 0.5, map.height as f32 - y - 0.5) * MINIMAP_SCALE
+            };
+            let from_minimap = |point: egui::Pos2| {
+                let offset = (point - rect.min) / MINIMAP_SCALE;
+                Vec2::new(offset.x - 0.5, map.height as f32 - offset.y - 0.5)
+            };
+
+            painter.image(
+                texture_id,
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+
+            for position in enemy_query.iter().filter(|position| {
+                map.get(position.x, position.y)
+                    .is_some_and(|tile| tile.view_status == ViewStatus::Seen)
+            }) {
+                painter.circle_filled(
+                    to_minimap(position.x as f32, position.y as f32),
+                    1.5,
+                    egui::Color32::RED,
+                );
+            }
+            if let Ok(position) = player_query.get_single() {
+                painter.circle_filled(
+                    to_minimap(position.x as f32, position.y as f32),
+                    2.0,
+                    egui::Color32::from_rgb(80, 200, 255),
+                );
+            }
+
+            let camera_position = camera_transform.translation.truncate();
+            let viewport_min = (camera_position + projection.area.min) / 12.0;
+            let viewport_max = (camera_position + projection.area.max) / 12.0;
+            painter.rect_stroke(
+                egui::Rect::from_two_pos(
+                    to_minimap(viewport_min.x, viewport_max.y),
+                    to_minimap(viewport_max.x, viewport_min.y),
+                ),
+                0.0,
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            );
+
+            if response.clicked() || response.dragged() {
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    pan_camera_to(
+                        from_minimap(pointer) * 12.0,
+                        &mut camera_transform,
+                        &mut mode,
+                    );
+                }
+            }
+        });
+}
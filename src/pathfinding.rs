@@ -0,0 +1,91 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    level_generation::map::{Map, ViewStatus},
+    position::{Position, PositionDelta},
+};
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 0),
+    (0, -1),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, -1),
+    (-1, 1),
+];
+
+//Breadth first search in all eight directions, over tiles can_enter allows, to the nearest tile
+//is_goal accepts. The path leaves out the start and ends on the goal, so it's empty when the start
+//is already a goal. None when no goal can be reached.
+pub fn path_to_nearest(
+    map: &Map,
+    start: Position,
+    can_enter: impl Fn(Position) -> bool,
+    is_goal: impl Fn(Position) -> bool,
+) -> Option<Vec<Position>> {
+    let mut came_from: HashMap<Position, Position> = HashMap::from([(start, start)]);
+    let mut frontier = VecDeque::from([start]);
+
+    while let Some(current) = frontier.pop_front() {
+        if is_goal(current) {
+            let mut path = Vec::new();
+            let mut step = current;
+            while step != start {
+                path.push(step);
+                step = came_from[&step];
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for (x, y) in DIRECTIONS {
+            let next = current + PositionDelta::new(x, y);
+            if came_from.contains_key(&next)
+                || map.get(next.x, next.y).is_none()
+                || !can_enter(next)
+            {
+                continue;
+            }
+            came_from.insert(next, current);
+            frontier.push_back(next);
+        }
+    }
+    None
+}
+
+impl Map {
+    //Shortest route between two tiles over tiles the player knows of, leaving out the start
+    pub fn known_path(&self, start: Position, goal: Position) -> Option<Vec<Position>> {
+        path_to_nearest(
+            self,
+            start,
+            |next| is_known_passable(self, next),
+            |next| next == goal,
+        )
+    }
+}
+
+//Step to take from one tile to a neighbouring one
+pub fn step_towards(from: Position, to: Position) -> PositionDelta {
+    PositionDelta::new(
+        to.x as isize - from.x as isize,
+        to.y as isize - from.y as isize,
+    )
+}
+
+//Whether the player knows the tile is there to walk on
+pub fn is_known_passable(map: &Map, position: Position) -> bool {
+    map.get(position.x, position.y)
+        .is_some_and(|tile| tile.passable && tile.view_status != ViewStatus::Unexplored)
+}
+
+//Known tiles next to unexplored ones, where exploring carries on from
+pub fn is_frontier(map: &Map, position: Position) -> bool {
+    DIRECTIONS.iter().any(|&(x, y)| {
+        let next = position + PositionDelta::new(x, y);
+        map.get(next.x, next.y)
+            .is_some_and(|tile| tile.view_status == ViewStatus::Unexplored)
+    })
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::TurnState,
+    game_state::{AppState, GameplaySet},
+    item::InventoryAction,
+    position::{Position, PositionDelta},
+    replay::ReplayPlayback,
+};
+
+pub struct PlayerActionPlugin;
+
+impl Plugin for PlayerActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerInput>()
+            .add_event::<PlayerAction>()
+            .add_systems(
+                Update,
+                //the frame the turn or the game resumes is left for the fov and other
+                //bookkeeping to catch up, so actions see the same world however fast they come
+                take_player_action
+                    .run_if(state_exists_and_equals(TurnState::Player).and_then(accepting_actions))
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Everything the player can do that changes the game. These are recorded into replays, so they
+//mustn't refer to entities, which aren't the same from one run to the next.
+#[derive(Event, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum PlayerAction {
+    Move(PositionDelta),
+    //lets the turn pass without doing anything
+    Wait,
+    PickUp,
+    Descend,
+    Inventory(InventoryAction),
+    //fires the equipped ranged weapon at a tile
+    Fire(Position),
+}
+
+//An action asked for through the keyboard or the UI, which only becomes a PlayerAction once
+//take_player_action accepts it
+#[derive(Event)]
+pub struct PlayerInput(pub PlayerAction);
+
+//Whether an action can be taken this frame, for anything else that has to act in step with the player
+pub fn accepting_actions(
+    turn_state: Res<State<TurnState>>,
+    app_state: Res<State<AppState>>,
+) -> bool {
+    !turn_state.is_changed() && !app_state.is_changed()
+}
+
+//Takes at most one action a frame: the first one asked for, or the next one from the replay being
+//played back. Systems carrying out actions run after this, so an action is always carried out on
+//the frame it was taken, which keeps replays from depending on frame timing.
+pub fn take_player_action(
+    mut inputs: EventReader<PlayerInput>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    let input = inputs.iter().next().map(|input| input.0);
+    inputs.clear();
+
+    let action = match playback {
+        Some(mut playback) => playback.actions.pop_front(),
+        None => input,
+    };
+    if let Some(action) = action {
+        actions.send(action);
+    }
+}
@@ -1,7 +1,8 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::ops::Add;
 
-#[derive(Component, Clone, Copy, PartialEq)]
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -11,9 +12,14 @@ impl Position {
     pub fn new(x: usize, y: usize) -> Self {
         Self { x, y }
     }
+
+    //chebyshev distance
+    pub fn distance(&self, other: &Position) -> usize {
+        std::cmp::max(self.x.abs_diff(other.x), self.y.abs_diff(other.y))
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub struct PositionDelta {
     pub x: isize,
     pub y: isize,
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_state::{AppState, GameplaySet},
+    message_log::{MessageKind, MessageLog},
+    player_action::{take_player_action, PlayerAction},
+    storage,
+};
+
+//Bumped whenever PlayerAction or the layout of ReplayFile changes, so older replays are refused
+pub const REPLAY_VERSION: u32 = 1;
+
+const REPLAY_NAME: &str = "replay.ron";
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySlot>()
+            .add_systems(
+                Update,
+                (
+                    record_actions
+                        .after(take_player_action)
+                        .run_if(resource_exists::<Replay>()),
+                    end_playback
+                        .after(take_player_action)
+                        .run_if(resource_exists::<ReplayPlayback>())
+                        .in_set(GameplaySet),
+                ),
+            )
+            .add_systems(OnEnter(AppState::GameOver), finish_replay)
+            .add_systems(OnEnter(AppState::Victory), finish_replay)
+            .add_systems(OnEnter(AppState::MainMenu), finish_replay);
+    }
+}
+
+//Every action taken in the current run, which together with the seed is enough to play it again
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub actions: Vec<PlayerAction>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            actions: Vec::new(),
+        }
+    }
+}
+
+//Present while a replay is being watched. The player's own input is ignored until it runs out.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub seed: u64,
+    pub actions: VecDeque<PlayerAction>,
+}
+
+impl From<Replay> for ReplayPlayback {
+    fn from(replay: Replay) -> Self {
+        Self {
+            seed: replay.seed,
+            actions: replay.actions.into(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplaySlot {
+    pub exists: bool,
+    //why the last attempt to watch the replay failed
+    pub error: Option<String>,
+}
+
+impl Default for ReplaySlot {
+    fn default() -> Self {
+        Self {
+            exists: storage::exists(REPLAY_NAME),
+            error: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    replay: Replay,
+}
+
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+pub fn read_replay() -> Result<Replay, String> {
+    let contents = storage::read(REPLAY_NAME)?;
+    let header: ReplayHeader =
+        ron::from_str(&contents).map_err(|error| format!("the replay is corrupt ({error})"))?;
+    if header.version != REPLAY_VERSION {
+        return Err(format!(
+            "the replay is from version {} of the replay format, this game reads version {REPLAY_VERSION}",
+            header.version
+        ));
+    }
+    ron::from_str(&contents)
+        .map(|replay_file: ReplayFile| replay_file.replay)
+        .map_err(|error| format!("the replay is corrupt ({error})"))
+}
+
+fn record_actions(mut actions: EventReader<PlayerAction>, mut replay: ResMut<Replay>) {
+    replay.actions.extend(actions.iter().copied());
+}
+
+fn end_playback(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut message_log: ResMut<MessageLog>,
+) {
+    if playback.actions.is_empty() {
+        commands.remove_resource::<ReplayPlayback>();
+        message_log.push(MessageKind::Info, "The replay has ended.");
+    }
+}
+
+//Writes out the run once it's over or abandoned. A replay being watched isn't written back,
+//unless the player took over after it ended.
+fn finish_replay(
+    mut commands: Commands,
+    replay: Option<Res<Replay>>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut slot: ResMut<ReplaySlot>,
+) {
+    commands.remove_resource::<ReplayPlayback>();
+    let Some(replay) = replay else {
+        return;
+    };
+    commands.remove_resource::<Replay>();
+    if playback.is_some() {
+        return;
+    }
+
+    let replay_file = ReplayFile {
+        version: REPLAY_VERSION,
+        replay: replay.clone(),
+    };
+    let result = ron::to_string(&replay_file)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| storage::write(REPLAY_NAME, &contents));
+    match result {
+        Ok(()) => slot.exists = true,
+        Err(error) => warn!("Couldn't write the replay: {error}"),
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Actor, Enemy, Player, TurnState},
+    fov::InView,
+    game_state::{AppState, GameplaySet},
+    message_log::{MessageKind, MessageLog},
+    player_action::{accepting_actions, take_player_action, PlayerAction, PlayerInput},
+    travel::travel,
+};
+
+pub struct RestPlugin;
+
+impl Plugin for RestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rest>()
+            .add_systems(OnExit(AppState::Playing), stop_resting)
+            .add_systems(
+                Update,
+                rest.after(travel)
+                    .before(take_player_action)
+                    .run_if(state_exists_and_equals(TurnState::Player).and_then(accepting_actions))
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Waits a turn at a time until the player is back to full health, or something interrupts them
+#[derive(Resource, Default)]
+pub struct Rest {
+    pub active: bool,
+    //health as of the last turn waited, so fresh damage interrupts the rest
+    last_health: Option<f32>,
+}
+
+impl Rest {
+    pub fn start(&mut self) {
+        self.active = true;
+        self.last_health = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.last_health = None;
+    }
+}
+
+fn stop_resting(mut rest: ResMut<Rest>) {
+    rest.stop();
+}
+
+pub fn rest(
+    mut rest: ResMut<Rest>,
+    player_query: Query<&Actor, With<Player>>,
+    enemy_query: Query<&Name, (With<Enemy>, With<InView>)>,
+    mut message_log: ResMut<MessageLog>,
+    mut inputs: ResMut<Events<PlayerInput>>,
+) {
+    if !rest.active {
+        return;
+    }
+    //anything else the player does this turn takes over from resting
+    if inputs.iter_current_update_events().next().is_some() {
+        rest.stop();
+        return;
+    }
+    let Ok(actor) = player_query.get_single() else {
+        return;
+    };
+
+    let starting = rest.last_health.is_none();
+    let hurt = rest.last_health.is_some_and(|health| actor.health < health);
+    rest.last_health = Some(actor.health);
+
+    if let Some(name) = enemy_query.iter().next() {
+        let text = if starting {
+            format!("You can't rest with a {name} in view.")
+        } else {
+            format!("You see a {name} and stop resting.")
+        };
+        message_log.push(MessageKind::Warning, text);
+        rest.stop();
+    } else if hurt {
+        message_log.push(MessageKind::Danger, "You are hurt and stop resting.");
+        rest.stop();
+    } else if actor.health >= actor.max_health {
+        let text = if starting {
+            "You are already at full health."
+        } else {
+            "You feel rested."
+        };
+        message_log.push(MessageKind::Info, text);
+        rest.stop();
+    } else {
+        inputs.send(PlayerInput(PlayerAction::Wait));
+    }
+}
@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+//Source of every random roll made during play, so its state can be saved along with the game
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct GameRng(pub Pcg64);
+
+impl GameRng {
+    //runs started from the same seed play out the same given the same actions, see Replay
+    pub fn from_seed(seed: u64) -> Self {
+        Self(Pcg64::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(Pcg64::from_entropy())
+    }
+}
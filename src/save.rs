@@ -0,0 +1,417 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{player_bundle, Actor, Dormant, Enemy, EnemyKind, Player, TurnCount, TurnState},
+    fov::{ghost_bundle, Ghost, LastSeen, Spotted},
+    game_state::{despawn_level, despawn_run, AppState},
+    item::{spawn_item, Equipment, Inventory, Item, ItemDefinitions},
+    level_generation::{map::Map, Depth},
+    lighting::spawn_light_sources,
+    message_log::{Message, MessageKind, MessageLog},
+    position::Position,
+    replay::Replay,
+    rng::GameRng,
+    sprite_atlas::Renderable,
+    status::StatusEffects,
+    storage,
+};
+
+//Bumped whenever the layout of SaveFile changes, so older saves are refused instead of misread
+pub const SAVE_VERSION: u32 = 3;
+
+const SAVE_NAME: &str = "savegame.ron";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveGame>()
+            .init_resource::<SaveSlot>()
+            .add_systems(
+                OnEnter(AppState::LoadingSave),
+                (
+                    despawn_level,
+                    despawn_run,
+                    apply_deferred,
+                    load_game,
+                    apply_deferred,
+                    spawn_light_sources.run_if(resource_exists::<Map>()),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                save_game.run_if(resource_exists::<Map>().and_then(on_event::<SaveGame>())),
+            );
+    }
+}
+
+//Saves the game in progress. Only sent on the player's turn, so no enemy is halfway through moving.
+#[derive(Event)]
+pub struct SaveGame;
+
+#[derive(Resource)]
+pub struct SaveSlot {
+    pub exists: bool,
+    //why the last attempt to load the save failed
+    pub error: Option<String>,
+}
+
+impl Default for SaveSlot {
+    fn default() -> Self {
+        Self {
+            exists: storage::exists(SAVE_NAME),
+            error: None,
+        }
+    }
+}
+
+//Items are saved by name rather than ItemKind, so saves survive changes to assets/items.ron
+#[derive(Serialize, Deserialize)]
+struct SavedItem {
+    name: String,
+    count: u32,
+    charges: Option<u32>,
+    //where the item lies, for items on the floor
+    position: Option<Position>,
+    //whether the player has seen it there
+    spotted: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+    position: Position,
+    actor: Actor,
+    status_effects: StatusEffects,
+    inventory: Vec<SavedItem>,
+    capacity: usize,
+    //indices into the inventory
+    weapon: Option<usize>,
+    armour: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    kind: EnemyKind,
+    name: String,
+    position: Position,
+    actor: Actor,
+    status_effects: StatusEffects,
+    dormant: bool,
+    last_seen: Option<Position>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedGhost {
+    name: String,
+    sprite_index: usize,
+    position: Position,
+    //index into the enemies, none if the enemy has since died
+    enemy: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    depth: u32,
+    turn: u32,
+    rng: GameRng,
+    //the run so far, so a loaded game still records a replay from its start
+    replay: Replay,
+    messages: VecDeque<Message>,
+    map: Map,
+    player: SavedPlayer,
+    enemies: Vec<SavedEnemy>,
+    ghosts: Vec<SavedGhost>,
+    items: Vec<SavedItem>,
+}
+
+//Read on its own first, so a save from another version is reported as such rather than as corrupt
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+fn save_item(
+    item_definitions: &ItemDefinitions,
+    item: &Item,
+    position: Option<Position>,
+    spotted: bool,
+) -> SavedItem {
+    SavedItem {
+        name: item_definitions[item.kind].name.clone(),
+        count: item.count,
+        charges: item.charges,
+        position,
+        spotted,
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn save_game(
+    mut events: EventReader<SaveGame>,
+    map: Res<Map>,
+    depth: Res<Depth>,
+    turn_count: Res<TurnCount>,
+    rng: Res<GameRng>,
+    replay: Res<Replay>,
+    item_definitions: Res<ItemDefinitions>,
+    player_query: Query<(&Position, &Actor, &StatusEffects, &Inventory, &Equipment), With<Player>>,
+    enemy_query: Query<
+        (
+            Entity,
+            &EnemyKind,
+            &Name,
+            &Position,
+            &Actor,
+            &StatusEffects,
+            Option<&Dormant>,
+            Option<&LastSeen>,
+        ),
+        With<Enemy>,
+    >,
+    ghost_query: Query<(&Ghost, &Name, &Position, &Renderable)>,
+    item_query: Query<(&Item, Option<&Position>, Option<&Spotted>)>,
+    mut message_log: ResMut<MessageLog>,
+    mut slot: ResMut<SaveSlot>,
+) {
+    events.clear();
+    let Ok((position, actor, status_effects, inventory, equipment)) = player_query.get_single()
+    else {
+        return;
+    };
+
+    let equipped_index = |slot: Option<Entity>| {
+        slot.and_then(|entity| inventory.items.iter().position(|held| *held == entity))
+    };
+    let player = SavedPlayer {
+        position: *position,
+        actor: *actor,
+        status_effects: status_effects.clone(),
+        inventory: inventory
+            .items
+            .iter()
+            .filter_map(|entity| item_query.get(*entity).ok())
+            .map(|(item, _, _)| save_item(&item_definitions, item, None, false))
+            .collect(),
+        capacity: inventory.capacity,
+        weapon: equipped_index(equipment.weapon),
+        armour: equipped_index(equipment.armour),
+    };
+
+    let enemy_entities: Vec<Entity> = enemy_query.iter().map(|(entity, ..)| entity).collect();
+    let enemies = enemy_query
+        .iter()
+        .map(
+            |(_, kind, name, position, actor, status_effects, dormant, last_seen)| SavedEnemy {
+                kind: *kind,
+                name: name.to_string(),
+                position: *position,
+                actor: *actor,
+                status_effects: status_effects.clone(),
+                dormant: dormant.is_some(),
+                last_seen: last_seen.map(|last_seen| last_seen.0),
+            },
+        )
+        .collect();
+    let ghosts = ghost_query
+        .iter()
+        .map(|(ghost, name, position, renderable)| SavedGhost {
+            name: name.to_string(),
+            sprite_index: renderable.sprite_index,
+            position: *position,
+            enemy: enemy_entities
+                .iter()
+                .position(|enemy| *enemy == ghost.enemy),
+        })
+        .collect();
+    let items = item_query
+        .iter()
+        .filter(|(_, position, _)| position.is_some())
+        .map(|(item, position, spotted)| {
+            save_item(
+                &item_definitions,
+                item,
+                position.copied(),
+                spotted.is_some(),
+            )
+        })
+        .collect();
+
+    let save_file = SaveFile {
+        version: SAVE_VERSION,
+        depth: depth.0,
+        turn: turn_count.0,
+        rng: rng.clone(),
+        replay: replay.clone(),
+        messages: message_log.messages.clone(),
+        map: map.clone(),
+        player,
+        enemies,
+        ghosts,
+        items,
+    };
+    let result = ron::to_string(&save_file)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| storage::write(SAVE_NAME, &contents));
+    match result {
+        Ok(()) => {
+            slot.exists = true;
+            message_log.push(MessageKind::Info, "Game saved.");
+        }
+        Err(error) => {
+            message_log.push(
+                MessageKind::Danger,
+                format!("Couldn't save the game: {error}"),
+            );
+        }
+    }
+}
+
+fn read_save_file() -> Result<SaveFile, String> {
+    let contents = storage::read(SAVE_NAME)?;
+    let header: SaveHeader =
+        ron::from_str(&contents).map_err(|error| format!("the save is corrupt ({error})"))?;
+    if header.version != SAVE_VERSION {
+        return Err(format!(
+            "the save is from version {} of the save format, this game reads version {SAVE_VERSION}",
+            header.version
+        ));
+    }
+    ron::from_str(&contents).map_err(|error| format!("the save is corrupt ({error})"))
+}
+
+fn load_game(
+    mut commands: Commands,
+    item_definitions: Res<ItemDefinitions>,
+    mut slot: ResMut<SaveSlot>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let result =
+        read_save_file().and_then(|save_file| restore(&mut commands, &item_definitions, save_file));
+    match result {
+        Ok(()) => {
+            slot.error = None;
+            next_state.set(AppState::Playing);
+        }
+        Err(error) => {
+            slot.error = Some(format!("Couldn't load the game: {error}"));
+            next_state.set(AppState::MainMenu);
+        }
+    }
+}
+
+fn restore(
+    commands: &mut Commands,
+    item_definitions: &ItemDefinitions,
+    save_file: SaveFile,
+) -> Result<(), String> {
+    //every item is looked up before anything is spawned, so a bad save leaves nothing behind
+    let to_item = |saved: &SavedItem| {
+        item_definitions
+            .find(&saved.name)
+            .map(|kind| Item {
+                kind,
+                count: saved.count,
+                charges: saved.charges,
+            })
+            .ok_or_else(|| format!("the save contains an unknown item, {}", saved.name))
+    };
+    let inventory_items = save_file
+        .player
+        .inventory
+        .iter()
+        .map(to_item)
+        .collect::<Result<Vec<Item>, String>>()?;
+    let ground_items = save_file
+        .items
+        .iter()
+        .map(|saved| to_item(saved).map(|item| (item, saved.position, saved.spotted)))
+        .collect::<Result<Vec<(Item, Option<Position>, bool)>, String>>()?;
+
+    let player = save_file.player;
+    let inventory_entities: Vec<Entity> = inventory_items
+        .into_iter()
+        .map(|item| spawn_item(commands, item_definitions, item, None))
+        .collect();
+    let equipped =
+        |index: Option<usize>| index.and_then(|index| inventory_entities.get(index).copied());
+    commands.spawn(player_bundle(player.position)).insert((
+        player.actor,
+        player.actor.base_stats,
+        player.status_effects,
+        Equipment {
+            weapon: equipped(player.weapon),
+            armour: equipped(player.armour),
+        },
+        Inventory {
+            items: inventory_entities.clone(),
+            capacity: player.capacity,
+        },
+    ));
+
+    for (item, position, spotted) in ground_items {
+        let item = spawn_item(commands, item_definitions, item, position);
+        if spotted {
+            commands.entity(item).insert(Spotted);
+        }
+    }
+
+    //out of view until the player's fov finds them, which also keeps a ghost from being left behind
+    let enemy_entities: Vec<Entity> = save_file
+        .enemies
+        .into_iter()
+        .map(|enemy| {
+            let entity = enemy.kind.spawn(commands, enemy.position);
+            let mut entity = commands.entity(entity);
+            entity.insert((
+                Name::new(enemy.name),
+                enemy.actor,
+                enemy.actor.base_stats,
+                enemy.status_effects,
+            ));
+            if !enemy.dormant {
+                entity.remove::<Dormant>();
+            }
+            if let Some(last_seen) = enemy.last_seen {
+                entity.insert(LastSeen(last_seen));
+            }
+            entity.id()
+        })
+        .collect();
+
+    for ghost in save_file.ghosts {
+        let enemy = ghost
+            .enemy
+            .and_then(|index| enemy_entities.get(index).copied())
+            .unwrap_or(Entity::PLACEHOLDER);
+        commands.spawn(ghost_bundle(
+            ghost.sprite_index,
+            Name::new(ghost.name),
+            ghost.position,
+            enemy,
+        ));
+    }
+
+    let mut map = save_file.map;
+    map.mark_loaded();
+    commands.insert_resource(map);
+    commands.insert_resource(Depth(save_file.depth));
+    commands.insert_resource(TurnCount(save_file.turn));
+    commands.insert_resource(save_file.rng);
+    commands.insert_resource(save_file.replay);
+    let mut message_log = MessageLog::default();
+    for message in save_file.messages {
+        message_log.push(message.kind, message.text);
+    }
+    message_log.push(MessageKind::Info, "Game loaded.");
+    commands.insert_resource(message_log);
+    //set directly, since entering the player's turn through NextState would advance the turn count
+    //and tick status effects a second time
+    commands.insert_resource(State::new(TurnState::Player));
+    commands.insert_resource(NextState::<TurnState>::default());
+    Ok(())
+}
@@ -36,4 +36,15 @@ fn setup(
 pub enum SpriteIndex {
     Player = 1648,
     Bat = 1862,
+    Torch = 3736,
+    Fungus = 1033,
+}
+
+//What an entity looks like: a sprite from the atlas, drawn at its position on the given layer.
+//The simulation only attaches this, the view turns it into an actual sprite.
+#[derive(Component, Clone, Copy)]
+pub struct Renderable {
+    pub sprite_index: usize,
+    //higher layers are drawn on top
+    pub layer: f32,
 }
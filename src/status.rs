@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Actor, Player, TurnState},
+    message_log::{MessageKind, MessageLog},
+    position::PositionDelta,
+};
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(TurnState::Player), tick_status_effects);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    Poisoned,
+    Burning,
+    Slowed,
+    Hasted,
+    Confused,
+    Invisible,
+    Regenerating,
+}
+
+impl StatusKind {
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusKind::Poisoned => "poisoned",
+            StatusKind::Burning => "burning",
+            StatusKind::Slowed => "slowed",
+            StatusKind::Hasted => "hasted",
+            StatusKind::Confused => "confused",
+            StatusKind::Invisible => "invisible",
+            StatusKind::Regenerating => "regenerating",
+        }
+    }
+
+    //statuses that cancel each other out when applied
+    fn opposite(&self) -> Option<StatusKind> {
+        match self {
+            StatusKind::Slowed => Some(StatusKind::Hasted),
+            StatusKind::Hasted => Some(StatusKind::Slowed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns_left: u32,
+    //number of times a stacking status has been applied
+    pub stacks: u32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    //Poison stacks up its damage, everything else only refreshes its duration
+    pub fn apply(&mut self, kind: StatusKind, turns: u32) {
+        if let Some(opposite) = kind.opposite() {
+            if self.has(opposite) {
+                self.effects.retain(|effect| effect.kind != opposite);
+                return;
+            }
+        }
+
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            effect.turns_left = effect.turns_left.max(turns);
+            if kind == StatusKind::Poisoned {
+                effect.stacks += 1;
+            }
+        } else {
+            self.effects.push(StatusEffect {
+                kind,
+                turns_left: turns,
+                stacks: 1,
+            });
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    //confused actors stumble in a random direction half of the time
+    pub fn adjust_movement<R: Rng>(&self, delta: PositionDelta, rng: &mut R) -> PositionDelta {
+        if !self.has(StatusKind::Confused) || rng.gen_bool(0.5) {
+            return delta;
+        }
+        loop {
+            let random_delta = PositionDelta::new(rng.gen_range(-1..=1), rng.gen_range(-1..=1));
+            if random_delta.x != 0 || random_delta.y != 0 {
+                return random_delta;
+            }
+        }
+    }
+
+    //how many times the actor gets to act on the given turn
+    pub fn actions_on_turn(&self, turn: u32) -> u32 {
+        if self.has(StatusKind::Hasted) {
+            2
+        } else if self.has(StatusKind::Slowed) {
+            turn % 2
+        } else {
+            1
+        }
+    }
+}
+
+fn tick_status_effects(
+    mut query: Query<(&mut Actor, &mut StatusEffects, &Name, Option<&Player>)>,
+    mut message_log: ResMut<MessageLog>,
+) {
+    for (mut actor, mut status_effects, name, player) in query.iter_mut() {
+        for effect in status_effects.effects.iter_mut() {
+            match effect.kind {
+                StatusKind::Poisoned => actor.health -= effect.stacks as f32,
+                StatusKind::Burning => actor.health -= 3.,
+                StatusKind::Regenerating => {
+                    actor.health = (actor.health + 2.).min(actor.max_health)
+                }
+                _ => {}
+            }
+            effect.turns_left = effect.turns_left.saturating_sub(1);
+        }
+
+        for effect in status_effects.effects.iter() {
+            if effect.turns_left > 0 {
+                continue;
+            }
+            let adjective = effect.kind.adjective();
+            if player.is_some() {
+                message_log.push(MessageKind::Info, format!("You are no longer {adjective}."));
+            } else {
+                message_log.push(
+                    MessageKind::Info,
+                    format!("The {name} is no longer {adjective}."),
+                );
+            }
+        }
+        status_effects
+            .effects
+            .retain(|effect| effect.turns_left > 0);
+    }
+}
@@ -0,0 +1,49 @@
+//Named blobs of text that persist between sessions: files next to the executable natively,
+//entries in the browser's local storage on the web
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn exists(name: &str) -> bool {
+    std::path::Path::new(name).exists()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Result<String, String> {
+    std::fs::read_to_string(name).map_err(|error| error.to_string())
+}
+
+//written to a temporary file first, so a failed write can't destroy what was there before
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, contents: &str) -> Result<(), String> {
+    let temporary_path = format!("{name}.tmp");
+    std::fs::write(&temporary_path, contents)
+        .and_then(|_| std::fs::rename(&temporary_path, name))
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| "local storage is unavailable".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn exists(name: &str) -> bool {
+    local_storage().is_ok_and(|storage| storage.get_item(name).ok().flatten().is_some())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Result<String, String> {
+    local_storage()?
+        .get_item(name)
+        .ok()
+        .flatten()
+        .ok_or_else(|| format!("{name} doesn't exist"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, contents: &str) -> Result<(), String> {
+    local_storage()?
+        .set_item(name, contents)
+        .map_err(|_| "local storage is full".to_string())
+}
@@ -0,0 +1,226 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{Actor, Enemy, Player, TurnState},
+    camera_controls::{cursor_world_position, MainCamera},
+    combat::trace_projectile,
+    game_state::{AppState, GameplaySet},
+    gamepad_controls::GamepadInput,
+    item::{Equipment, InventoryAction, Item, ItemDefinitions},
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::{Map, ViewStatus},
+    player_action::{PlayerAction, PlayerInput},
+    position::Position,
+    world_to_map_position,
+};
+
+pub struct TargetingPlugin;
+
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TargetSelection>()
+            .add_systems(OnEnter(AppState::NewGame), reset_target_selection)
+            .add_systems(OnEnter(AppState::LoadingSave), reset_target_selection)
+            .add_systems(
+                Update,
+                (select_target, start_firing)
+                    .chain()
+                    .run_if(state_exists_and_equals(TurnState::Player))
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum TargetAction {
+    //the item's slot in the player's inventory
+    UseItem(usize),
+    Fire,
+}
+
+#[derive(Clone, Copy)]
+pub struct PendingTarget {
+    pub action: TargetAction,
+    pub range: usize,
+}
+
+//Set while the player is picking a tile for a targeted item or a ranged attack
+#[derive(Resource, Default)]
+pub struct TargetSelection {
+    pub pending: Option<PendingTarget>,
+    //tile currently aimed at, moved by cycling through enemies or by the mouse
+    pub focus: Option<Position>,
+}
+
+impl TargetSelection {
+    pub fn begin(&mut self, action: TargetAction, range: usize) {
+        self.pending = Some(PendingTarget { action, range });
+        self.focus = None;
+    }
+}
+
+//A new or loaded run starts with nothing being aimed at
+fn reset_target_selection(mut selection: ResMut<TargetSelection>) {
+    *selection = TargetSelection::default();
+}
+
+//Enemies the player can currently see, closest first
+fn visible_enemies(
+    map: &Map,
+    player_position: &Position,
+    enemy_query: &Query<&Position, With<Enemy>>,
+) -> Vec<Position> {
+    let mut enemies: Vec<Position> = enemy_query
+        .iter()
+        .filter(|position| {
+            map.get(position.x, position.y)
+                .is_some_and(|tile| tile.view_status == ViewStatus::Seen)
+        })
+        .copied()
+        .collect();
+    enemies.sort_by_key(|position| position.distance(player_position));
+    enemies
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_firing(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    gamepad: GamepadInput,
+    player_query: Query<(&Position, &Equipment), With<Player>>,
+    enemy_query: Query<&Position, With<Enemy>>,
+    item_query: Query<&Item>,
+    item_definitions: Res<ItemDefinitions>,
+    map: Res<Map>,
+    mut selection: ResMut<TargetSelection>,
+) {
+    let fire = bindings.just_pressed(&keyboard, KeyAction::Fire)
+        || gamepad.just_pressed(GamepadButtonType::RightTrigger2);
+    if selection.pending.is_some() || !fire {
+        return;
+    }
+    let (player_position, equipment) = player_query.single();
+    let Some(ranged) = equipment
+        .weapon
+        .and_then(|weapon| item_query.get(weapon).ok())
+        .and_then(|weapon| item_definitions[weapon.kind].ranged.as_ref())
+    else {
+        return;
+    };
+
+    selection.begin(TargetAction::Fire, ranged.range);
+    selection.focus = visible_enemies(&map, player_position, &enemy_query)
+        .first()
+        .copied();
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn select_target(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mouse: Res<Input<MouseButton>>,
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    gamepad: GamepadInput,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    player_query: Query<&Position, With<Player>>,
+    enemy_query: Query<&Position, With<Enemy>>,
+    actor_query: Query<(Entity, &Position), (With<Actor>, Without<Player>)>,
+    map: Res<Map>,
+    mut selection: ResMut<TargetSelection>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    let Some(pending) = selection.pending else {
+        cursor_moved.clear();
+        return;
+    };
+    if bindings.just_pressed(&keyboard, KeyAction::Cancel)
+        || gamepad.just_pressed(GamepadButtonType::East)
+    {
+        selection.pending = None;
+        return;
+    }
+
+    let player_position = *player_query.single();
+    let (camera, camera_transform) = camera_query.single();
+    let hovered = cursor_world_position(window_query.single(), camera, camera_transform)
+        .and_then(world_to_map_position);
+
+    if cursor_moved.iter().count() > 0 && hovered.is_some() {
+        selection.focus = hovered;
+    }
+    //the shoulder buttons cycle either way through the enemies in view
+    let next = bindings.just_pressed(&keyboard, KeyAction::NextTarget)
+        || gamepad.just_pressed(GamepadButtonType::RightTrigger);
+    let previous = gamepad.just_pressed(GamepadButtonType::LeftTrigger);
+    if next || previous {
+        let enemies = visible_enemies(&map, &player_position, &enemy_query);
+        let current = selection
+            .focus
+            .and_then(|focus| enemies.iter().position(|enemy| *enemy == focus));
+        let index = match current {
+            Some(index) if previous => (index + enemies.len() - 1) % enemies.len(),
+            Some(index) => (index + 1) % enemies.len(),
+            None => 0,
+        };
+        selection.focus = enemies.get(index).copied().or(selection.focus);
+    }
+
+    egui::Area::new("targeting_hint")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{}/shoulders: next target   {}/click/A: confirm   {}/B: cancel",
+                bindings.describe(&[KeyAction::NextTarget]),
+                bindings.describe(&[KeyAction::Confirm]),
+                bindings.describe(&[KeyAction::Cancel])
+            ));
+        });
+
+    let Some(focus) = selection.focus else {
+        return;
+    };
+    let valid = focus.distance(&player_position) <= pending.range
+        && map
+            .get(focus.x, focus.y)
+            .is_some_and(|tile| tile.view_status == ViewStatus::Seen);
+
+    let color = if valid { Color::YELLOW } else { Color::RED };
+    let actors: Vec<(Entity, Position)> = actor_query
+        .iter()
+        .map(|(entity, position)| (entity, *position))
+        .collect();
+    let (impact, _) = trace_projectile(&map, player_position, focus, &actors);
+    gizmos.line_2d(
+        Vec2::new(player_position.x as f32, player_position.y as f32) * 12.0,
+        Vec2::new(impact.x as f32, impact.y as f32) * 12.0,
+        color,
+    );
+    gizmos.rect_2d(
+        Vec2::new(focus.x as f32, focus.y as f32) * 12.0,
+        0.0,
+        Vec2::splat(12.0),
+        color,
+    );
+
+    let clicked = mouse.just_pressed(MouseButton::Left)
+        && hovered == Some(focus)
+        && !contexts.ctx_mut().is_pointer_over_area();
+    let confirmed = bindings.just_pressed(&keyboard, KeyAction::Confirm)
+        || gamepad.just_pressed(GamepadButtonType::South)
+        || (bindings.just_pressed(&keyboard, KeyAction::Fire)
+            && matches!(pending.action, TargetAction::Fire));
+    if valid && (clicked || confirmed) {
+        let action = match pending.action {
+            TargetAction::UseItem(slot) => {
+                PlayerAction::Inventory(InventoryAction::Use(slot, Some(focus)))
+            }
+            TargetAction::Fire => PlayerAction::Fire(focus),
+        };
+        inputs.send(PlayerInput(action));
+        selection.pending = None;
+    }
+}
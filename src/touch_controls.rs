@@ -0,0 +1,142 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::EguiContexts;
+
+use crate::{
+    actor::{Player, TurnState},
+    auto_explore::{explore, AutoExplore},
+    camera_controls::MainCamera,
+    game_state::GameplaySet,
+    level_generation::map::Map,
+    look::LookCursor,
+    pathfinding::is_known_passable,
+    player_action::{take_player_action, PlayerAction, PlayerInput},
+    position::{Position, PositionDelta},
+    rest::Rest,
+    targeting::TargetSelection,
+    travel::Travel,
+    world_to_map_position,
+};
+
+//Further than this many pixels from where the finger went down is a swipe rather than a tap
+const TAP_TOLERANCE: f32 = 12.0;
+//How long, in seconds, a finger has to stay down to travel rather than tap
+const HOLD_TIME: f32 = 0.5;
+
+//Taps on the map for the wasm build on phones and tablets. Pinching and panning the camera
+//with two fingers is in camera_controls.
+pub struct TouchControlsPlugin;
+
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            touch_input
+                .before(explore)
+                .before(take_player_action)
+                .run_if(state_exists_and_equals(TurnState::Player))
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+//A single finger that is down on the map, and might still become a tap or a hold
+struct Press {
+    id: u64,
+    held_for: f32,
+}
+
+//Map tile under a point on the screen, as touches give it in physical pixels
+fn touched_tile(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    touch_position: Vec2,
+) -> Option<Position> {
+    camera
+        .viewport_to_world_2d(
+            camera_transform,
+            touch_position / window.scale_factor() as f32,
+        )
+        .and_then(world_to_map_position)
+}
+
+//Tapping the player waits, tapping a tile next to them moves or attacks there, and holding a
+//finger down on a known tile travels to it
+#[allow(clippy::too_many_arguments)]
+fn touch_input(
+    mut contexts: EguiContexts,
+    touches: Res<Touches>,
+    time: Res<Time>,
+    mut press: Local<Option<Press>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    player_query: Query<&Position, With<Player>>,
+    map: Res<Map>,
+    selection: Res<TargetSelection>,
+    look_cursor: Res<LookCursor>,
+    mut auto_explore: ResMut<AutoExplore>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    //a second finger makes it a pinch or a pan, which the camera takes care of
+    if touches.iter().count() > 1 {
+        *press = None;
+        return;
+    }
+    if let Some(touch) = touches.iter_just_pressed().next() {
+        let busy = selection.pending.is_some()
+            || look_cursor.position.is_some()
+            || contexts.ctx_mut().is_pointer_over_area();
+        *press = (!busy).then_some(Press {
+            id: touch.id(),
+            held_for: 0.0,
+        });
+    }
+    let Some(current) = press.as_mut() else {
+        return;
+    };
+    let Some(touch) = touches
+        .get_pressed(current.id)
+        .or(touches.get_released(current.id))
+    else {
+        *press = None;
+        return;
+    };
+    if touch.distance().length() > TAP_TOLERANCE {
+        *press = None;
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform)), Ok(player_position)) = (
+        window_query.get_single(),
+        camera_query.get_single(),
+        player_query.get_single(),
+    ) else {
+        return;
+    };
+    let Some(tile) = touched_tile(window, camera, camera_transform, touch.start_position()) else {
+        return;
+    };
+
+    current.held_for += time.delta_seconds();
+    if current.held_for >= HOLD_TIME {
+        if is_known_passable(&map, tile) {
+            auto_explore.stop();
+            rest.stop();
+            travel.start(tile);
+        }
+        *press = None;
+    } else if touches.just_released(current.id) {
+        let delta = PositionDelta::new(
+            tile.x as isize - player_position.x as isize,
+            tile.y as isize - player_position.y as isize,
+        );
+        if delta.x == 0 && delta.y == 0 {
+            inputs.send(PlayerInput(PlayerAction::Wait));
+        } else if delta.x.abs() <= 1 && delta.y.abs() <= 1 {
+            inputs.send(PlayerInput(PlayerAction::Move(delta)));
+        }
+        *press = None;
+    }
+}
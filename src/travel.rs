@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Actor, Enemy, Player, TurnState},
+    auto_explore::explore,
+    fov::InView,
+    game_state::{AppState, GameplaySet},
+    level_generation::map::Map,
+    message_log::{MessageKind, MessageLog},
+    pathfinding::step_towards,
+    player_action::{accepting_actions, take_player_action, PlayerAction, PlayerInput},
+    position::Position,
+};
+
+pub struct TravelPlugin;
+
+impl Plugin for TravelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Travel>()
+            .add_systems(OnExit(AppState::Playing), stop_travelling)
+            .add_systems(
+                Update,
+                travel
+                    .after(explore)
+                    .before(take_player_action)
+                    .run_if(state_exists_and_equals(TurnState::Player).and_then(accepting_actions))
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+//Walks the player one step a turn along the known route to a tile, until they get there or
+//something interrupts them
+#[derive(Resource, Default)]
+pub struct Travel {
+    pub destination: Option<Position>,
+    //enemies in view and the player's health as of the last step, so only enemies coming into
+    //view and fresh damage interrupt the journey
+    enemies_in_view: HashSet<Entity>,
+    last_health: Option<f32>,
+}
+
+impl Travel {
+    pub fn start(&mut self, destination: Position) {
+        *self = Travel {
+            destination: Some(destination),
+            ..default()
+        };
+    }
+
+    pub fn stop(&mut self) {
+        *self = Travel::default();
+    }
+}
+
+fn stop_travelling(mut travel: ResMut<Travel>) {
+    travel.stop();
+}
+
+#[allow(clippy::type_complexity)]
+pub fn travel(
+    mut travel: ResMut<Travel>,
+    player_query: Query<(&Position, &Actor), With<Player>>,
+    enemy_query: Query<(Entity, &Name), (With<Enemy>, With<InView>)>,
+    map: Res<Map>,
+    mut message_log: ResMut<MessageLog>,
+    mut inputs: ResMut<Events<PlayerInput>>,
+) {
+    let Some(destination) = travel.destination else {
+        return;
+    };
+    //anything else the player does this turn takes over from travelling
+    if inputs.iter_current_update_events().next().is_some() {
+        travel.stop();
+        return;
+    }
+    let Ok((position, actor)) = player_query.get_single() else {
+        return;
+    };
+
+    let starting = travel.last_health.is_none();
+    let spotted = enemy_query
+        .iter()
+        .find(|(entity, _)| !starting && !travel.enemies_in_view.contains(entity));
+    let hurt = travel
+        .last_health
+        .is_some_and(|health| actor.health < health);
+    travel.enemies_in_view = enemy_query.iter().map(|(entity, _)| entity).collect();
+    travel.last_health = Some(actor.health);
+
+    if let Some((_, name)) = spotted {
+        message_log.push(
+            MessageKind::Warning,
+            format!("You see a {name} and stop travelling."),
+        );
+        travel.stop();
+    } else if hurt {
+        message_log.push(MessageKind::Danger, "You are hurt and stop travelling.");
+        travel.stop();
+    } else if *position == destination {
+        travel.stop();
+    } else if let Some(path) = map.known_path(*position, destination) {
+        inputs.send(PlayerInput(PlayerAction::Move(step_towards(
+            *position, path[0],
+        ))));
+    } else {
+        message_log.push(MessageKind::Warning, "You don't know a way there.");
+        travel.stop();
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::Player,
+    auto_explore::AutoExplore,
+    camera_controls::{cursor_world_position, MainCamera},
+    fov::Spotted,
+    game_state::GameplaySet,
+    item::{Item, ItemDefinitions},
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::{Map, ViewStatus},
+    look::LookCursor,
+    pathfinding::is_known_passable,
+    position::Position,
+    targeting::TargetSelection,
+    travel::Travel,
+    world_to_map_position,
+};
+
+//Further than this many pixels between pressing and releasing the mouse is a drag of the
+//camera rather than a click
+const CLICK_TOLERANCE: f32 = 4.0;
+
+pub struct TravelViewPlugin;
+
+impl Plugin for TravelViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TravelMenu>().add_systems(
+            Update,
+            (
+                toggle_travel_menu,
+                travel_menu,
+                click_to_travel,
+                preview_route,
+            )
+                .chain()
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+//Window listing the known stairs and floor items to travel to
+#[derive(Resource, Default)]
+pub struct TravelMenu {
+    pub open: bool,
+}
+
+//Route last worked out for the preview, kept until the player or the tile it leads to changes
+#[derive(Default)]
+struct RoutePreview {
+    from: Option<Position>,
+    to: Option<Position>,
+    route: Option<Vec<Position>>,
+}
+
+fn hovered_tile(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<Position> {
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    cursor_world_position(window_query.get_single().ok()?, camera, camera_transform)
+        .and_then(world_to_map_position)
+}
+
+fn set_off(destination: Position, travel: &mut Travel, auto_explore: &mut AutoExplore) {
+    auto_explore.stop();
+    travel.start(destination);
+}
+
+fn toggle_travel_menu(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut menu: ResMut<TravelMenu>,
+) {
+    if bindings.just_pressed(&keyboard, KeyAction::Travel) {
+        menu.open = !menu.open;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn travel_menu(
+    mut contexts: EguiContexts,
+    mut menu: ResMut<TravelMenu>,
+    player_query: Query<&Position, With<Player>>,
+    item_query: Query<(&Item, &Position), With<Spotted>>,
+    item_definitions: Res<ItemDefinitions>,
+    map: Res<Map>,
+    mut travel: ResMut<Travel>,
+    mut auto_explore: ResMut<AutoExplore>,
+) {
+    if !menu.open {
+        return;
+    }
+    let Ok(player_position) = player_query.get_single() else {
+        return;
+    };
+    let is_known = |position: &Position| {
+        map.get(position.x, position.y)
+            .is_some_and(|tile| tile.view_status != ViewStatus::Unexplored)
+    };
+
+    let stairs = (0..map.width)
+        .flat_map(|x| (0..map.height).map(move |y| Position::new(x, y)))
+        .filter(|position| is_known(position) && map.get(position.x, position.y).unwrap().stairs)
+        .min_by_key(|position| position.distance(player_position));
+    let mut destinations: Vec<(String, Position)> = item_query
+        .iter()
+        .map(|(item, position)| (item_definitions[item.kind].describe(item.count), *position))
+        .collect();
+    destinations.sort_by_key(|(_, position)| position.distance(player_position));
+    if let Some(stairs) = stairs {
+        destinations.insert(0, ("the stairs down".to_string(), stairs));
+    }
+
+    let mut open = menu.open;
+    let mut chosen = None;
+    egui::Window::new("Travel")
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if destinations.is_empty() {
+                ui.label("You don't know of anywhere to travel to.");
+            }
+            for (name, position) in &destinations {
+                let distance = position.distance(player_position);
+                if ui.button(format!("{name} ({distance} away)")).clicked() {
+                    chosen = Some(*position);
+                }
+            }
+        });
+    menu.open = open;
+
+    if let Some(destination) = chosen {
+        set_off(destination, &mut travel, &mut auto_explore);
+        menu.open = false;
+    }
+}
+
+//A click, as opposed to a drag, on a known tile travels there
+#[allow(clippy::too_many_arguments)]
+fn click_to_travel(
+    mut contexts: EguiContexts,
+    mut pressed_at: Local<Option<Vec2>>,
+    mouse: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Res<Map>,
+    selection: Res<TargetSelection>,
+    look_cursor: Res<LookCursor>,
+    mut travel: ResMut<Travel>,
+    mut auto_explore: ResMut<AutoExplore>,
+) {
+    let cursor = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    //clicks while aiming or looking around belong to those
+    let busy = selection.pending.is_some()
+        || look_cursor.position.is_some()
+        || contexts.ctx_mut().is_pointer_over_area();
+
+    if mouse.just_pressed(MouseButton::Left) {
+        *pressed_at = if busy { None } else { cursor };
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(pressed_at) = pressed_at.take() else {
+        return;
+    };
+    if cursor.is_none_or(|cursor| cursor.distance(pressed_at) > CLICK_TOLERANCE) {
+        return;
+    }
+    if let Some(destination) = hovered_tile(&window_query, &camera_query)
+        .filter(|destination| is_known_passable(&map, *destination))
+    {
+        set_off(destination, &mut travel, &mut auto_explore);
+    }
+}
+
+//Draws the route the player would take to the hovered tile, or the one they're travelling along
+#[allow(clippy::too_many_arguments)]
+fn preview_route(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    mut preview: Local<RoutePreview>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    player_query: Query<&Position, With<Player>>,
+    map: Res<Map>,
+    selection: Res<TargetSelection>,
+    travel: Res<Travel>,
+) {
+    let Ok(player_position) = player_query.get_single() else {
+        return;
+    };
+    let hovered = if selection.pending.is_some() || contexts.ctx_mut().is_pointer_over_area() {
+        None
+    } else {
+        hovered_tile(&window_query, &camera_query)
+            .filter(|position| is_known_passable(&map, *position))
+    };
+    let Some(destination) = travel.destination.or(hovered) else {
+        return;
+    };
+
+    if preview.from != Some(*player_position) || preview.to != Some(destination) || map.is_changed()
+    {
+        *preview = RoutePreview {
+            from: Some(*player_position),
+            to: Some(destination),
+            route: map.known_path(*player_position, destination),
+        };
+    }
+    let Some(route) = &preview.route else {
+        return;
+    };
+
+    let to_world = |position: &Position| Vec2::new(position.x as f32, position.y as f32) * 12.0;
+    gizmos.linestrip_2d(
+        std::iter::once(player_position).chain(route).map(to_world),
+        Color::CYAN,
+    );
+    gizmos.rect_2d(to_world(&destination), 0.0, Vec2::splat(12.0), Color::CYAN);
+}
@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    actor::{Actor, CombatStats, Player, TurnCount},
+    auto_explore::AutoExplore,
+    effect::Targeting,
+    game_state::{AppState, GameplaySet},
+    item::{Equipment, Inventory, InventoryAction, Item, ItemDefinitions},
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::Map,
+    level_generation::Depth,
+    message_log::{MessageKind, MessageLog},
+    player_action::{PlayerAction, PlayerInput},
+    targeting::{TargetAction, TargetSelection},
+    travel::Travel,
+};
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InventoryScreen>()
+            .add_systems(OnEnter(AppState::NewGame), close_inventory_screen)
+            .add_systems(OnEnter(AppState::LoadingSave), close_inventory_screen)
+            .add_systems(
+                Update,
+                (
+                    hud.run_if(resource_exists::<Map>()),
+                    (toggle_inventory_screen, inventory_screen)
+                        .chain()
+                        .in_set(GameplaySet),
+                ),
+            );
+    }
+}
+
+fn message_color(kind: MessageKind) -> egui::Color32 {
+    match kind {
+        MessageKind::Info => egui::Color32::LIGHT_GRAY,
+        MessageKind::Good => egui::Color32::LIGHT_GREEN,
+        MessageKind::Warning => egui::Color32::GOLD,
+        MessageKind::Danger => egui::Color32::LIGHT_RED,
+    }
+}
+
+fn hud(
+    mut contexts: EguiContexts,
+    player_query: Query<&Actor, With<Player>>,
+    depth: Res<Depth>,
+    turn_count: Res<TurnCount>,
+    message_log: Res<MessageLog>,
+    auto_explore: Res<AutoExplore>,
+    travel: Res<Travel>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    egui::Window::new("Status")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if let Ok(actor) = player_query.get_single() {
+                let health = (actor.health / actor.max_health).clamp(0.0, 1.0);
+                ui.add(
+                    egui::ProgressBar::new(health)
+                        .desired_width(180.0)
+                        .fill(egui::Color32::DARK_RED)
+                        .text(format!(
+                            "HP {:.0}/{:.0}",
+                            actor.health.max(0.0),
+                            actor.max_health
+                        )),
+                );
+            }
+            ui.label(format!("Depth {}   Turn {}", depth.0, turn_count.0));
+            if auto_explore.active {
+                ui.label("Exploring...");
+            }
+            if travel.destination.is_some() {
+                ui.label("Travelling...");
+            }
+        });
+
+    egui::Window::new("Messages")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .title_bar(false)
+        .default_size(egui::vec2(420.0, 140.0))
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    for message in &message_log.messages {
+                        ui.colored_label(message_color(message.kind), &message.text);
+                    }
+                });
+        });
+}
+
+#[derive(Resource, Default)]
+pub struct InventoryScreen {
+    pub open: bool,
+}
+
+//A new or loaded run starts with the inventory closed
+fn close_inventory_screen(mut screen: ResMut<InventoryScreen>) {
+    screen.open = false;
+}
+
+fn toggle_inventory_screen(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut screen: ResMut<InventoryScreen>,
+) {
+    if bindings.just_pressed(&keyboard, KeyAction::Inventory) {
+        screen.open = !screen.open;
+    }
+}
+
+fn inventory_screen(
+    mut contexts: EguiContexts,
+    mut screen: ResMut<InventoryScreen>,
+    player_query: Query<(&Actor, &CombatStats, &Inventory, &Equipment), With<Player>>,
+    item_query: Query<&Item>,
+    item_definitions: Res<ItemDefinitions>,
+    mut target_selection: ResMut<TargetSelection>,
+    mut inputs: EventWriter<PlayerInput>,
+) {
+    let Ok((actor, stats, inventory, equipment)) = player_query.get_single() else {
+        return;
+    };
+
+    let mut close_screen = false;
+    egui::Window::new("Inventory")
+        .open(&mut screen.open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "Health: {:.0}/{:.0}   Attack: {:.0}   Defense: {:.0}",
+                actor.health, actor.max_health, stats.attack, stats.defense
+            ));
+            ui.label(format!(
+                "Carrying {}/{}",
+                inventory.items.len(),
+                inventory.capacity
+            ));
+            ui.separator();
+
+            let mut send = |action| inputs.send(PlayerInput(PlayerAction::Inventory(action)));
+            for (slot, entity) in inventory.items.iter().enumerate() {
+                let Ok(item) = item_query.get(*entity) else {
+                    continue;
+                };
+                let definition = &item_definitions[item.kind];
+                ui.horizontal(|ui| {
+                    let mut label = definition.name.clone();
+                    if item.count > 1 {
+                        label += &format!(" x{}", item.count);
+                    }
+                    if let Some(charges) = item.charges {
+                        label += &format!(" ({charges} charges)");
+                    }
+                    if equipment.is_equipped(*entity) {
+                        label += " (equipped)";
+                    }
+                    ui.label(label);
+
+                    if definition.is_usable() && ui.button("Use").clicked() {
+                        match definition.targeting {
+                            Targeting::User => send(InventoryAction::Use(slot, None)),
+                            Targeting::Tile { range } => {
+                                target_selection.begin(TargetAction::UseItem(slot), range);
+                                close_screen = true;
+                            }
+                        }
+                    }
+                    if definition.equipment.is_some() {
+                        if equipment.is_equipped(*entity) {
+                            if ui.button("Unequip").clicked() {
+                                send(InventoryAction::Unequip(slot));
+                            }
+                        } else if ui.button("Equip").clicked() {
+                            send(InventoryAction::Equip(slot));
+                        }
+                    }
+                    if ui.button("Drop").clicked() {
+                        send(InventoryAction::Drop(slot));
+                    }
+                });
+            }
+        });
+
+    if close_screen {
+        screen.open = false;
+    }
+}
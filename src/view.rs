@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+
+use crate::{
+    actor::{Enemy, Player},
+    animation::Animation,
+    camera_controls::{clamp_camera, CameraMode, CameraSettings, MainCamera},
+    fov::{FovChanges, Ghost, InView, Spotted},
+    item::Item,
+    key_bindings::{KeyAction, KeyBindings},
+    level_generation::map::{Map, ViewStatus},
+    lighting::{LightMap, LightSource},
+    position::Position,
+    sprite_atlas::{Renderable, SpriteAtlas},
+    status::{StatusEffects, StatusKind},
+};
+
+//Draws the simulation. Nothing here changes the state of the game, it only follows it.
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                add_sprites,
+                apply_deferred,
+                (
+                    update_transforms,
+                    update_visibility,
+                    tint_sprites.run_if(resource_exists::<Map>()),
+                    show_invisibility,
+                    follow_player.before(clamp_camera),
+                ),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn add_sprites(
+    mut commands: Commands,
+    atlas: Res<SpriteAtlas>,
+    query: Query<(Entity, &Renderable, Option<&Position>, Option<&Ghost>), Added<Renderable>>,
+) {
+    for (entity, renderable, position, ghost) in query.iter() {
+        let (x, y) = position.map_or((0, 0), |position| (position.x, position.y));
+        //ghosts are drawn faded, so they aren't mistaken for the enemy itself
+        let color = if ghost.is_some() {
+            Color::rgba(0.6, 0.6, 0.6, 0.4)
+        } else {
+            Color::WHITE
+        };
+        let translation = Vec3::new(x as f32, y as f32, renderable.layer) * Vec3::splat(12.0);
+        commands.entity(entity).insert((
+            SpriteSheetBundle {
+                texture_atlas: atlas.handle.clone(),
+                sprite: TextureAtlasSprite {
+                    index: renderable.sprite_index,
+                    color,
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation,
+                    ..Default::default()
+                },
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            Animation::at(translation.truncate()),
+        ));
+    }
+}
+
+//Sprites slide over to their new tile, see animation
+pub fn update_transforms(mut query: Query<(&mut Animation, &Position), Changed<Position>>) {
+    for (mut animation, position) in query.iter_mut() {
+        animation.move_to(Vec2::new(position.x as f32, position.y as f32) * 12.0);
+    }
+}
+
+//Carried items have no position, enemies are only drawn while the player can see them and floor
+//items once the player has seen them
+#[allow(clippy::type_complexity)]
+fn update_visibility(
+    mut query: Query<
+        (
+            &mut Visibility,
+            Option<&Position>,
+            Option<&Enemy>,
+            Option<&InView>,
+            Option<&Item>,
+            Option<&Spotted>,
+        ),
+        With<Renderable>,
+    >,
+) {
+    for (mut visibility, position, enemy, in_view, item, spotted) in query.iter_mut() {
+        let visible = position.is_some()
+            && (enemy.is_none() || in_view.is_some())
+            && (item.is_none() || spotted.is_some());
+        visibility.set_if_neq(if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+//Items and light sources take the colour of their tile, enemies the light they stand in
+#[allow(clippy::type_complexity)]
+pub fn tint_sprites(
+    map: Res<Map>,
+    light_map: Option<Res<LightMap>>,
+    changes: Res<FovChanges>,
+    mut query: Query<
+        (&mut TextureAtlasSprite, Ref<Position>),
+        (
+            Or<(With<Item>, With<LightSource>)>,
+            Without<Player>,
+            Without<Enemy>,
+        ),
+    >,
+    mut enemy_query: Query<(&mut TextureAtlasSprite, &Position), (With<Enemy>, With<InView>)>,
+) {
+    let tint = |position: &Position| {
+        light_map.as_ref().map_or(Color::WHITE, |light_map| {
+            light_map.tint(position.x, position.y)
+        })
+    };
+
+    let light_changed = light_map
+        .as_ref()
+        .is_some_and(|light_map| light_map.is_changed());
+    for (mut sprite, position) in query.iter_mut() {
+        if !position.is_changed()
+            && !light_changed
+            && !changes.tiles.contains(&(position.x, position.y))
+        {
+            continue;
+        }
+        sprite.color = match map.get(position.x, position.y).unwrap().view_status {
+            ViewStatus::Seen => tint(&position),
+            ViewStatus::Revealed => Color::GRAY,
+            ViewStatus::Unexplored => Color::BLACK,
+        };
+    }
+
+    for (mut sprite, position) in enemy_query.iter_mut() {
+        sprite.color = tint(position);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn show_invisibility(
+    mut query: Query<
+        (&mut TextureAtlasSprite, &StatusEffects),
+        (With<Player>, Changed<StatusEffects>),
+    >,
+) {
+    for (mut sprite, status_effects) in query.iter_mut() {
+        let alpha = if status_effects.has(StatusKind::Invisible) {
+            0.4
+        } else {
+            1.0
+        };
+        sprite.color.set_a(alpha);
+    }
+}
+
+//Eases the camera towards the player, unless it has been moved away to look around. Moving, or
+//the recentre key, brings it back.
+#[allow(clippy::too_many_arguments)]
+pub fn follow_player(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    mut mode: ResMut<CameraMode>,
+    mut last_position: Local<Option<Position>>,
+    player_query: Query<Ref<Position>, With<Player>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let (Ok(position), Ok(mut camera_transform)) =
+        (player_query.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+    if position.is_changed() || bindings.just_pressed(&keyboard, KeyAction::Recenter) {
+        *mode = CameraMode::Follow;
+    }
+    if *mode != CameraMode::Follow {
+        return;
+    }
+
+    //a new run, a new level or a teleport jumps straight there rather than sweeping across the map
+    let jump = last_position.is_none_or(|last| last.distance(&position) > 1);
+    *last_position = Some(*position);
+
+    let target = Vec2::new(position.x as f32, position.y as f32) * 12.0;
+    let current = camera_transform.translation.truncate();
+    let eased = if jump {
+        target
+    } else {
+        current.lerp(
+            target,
+            1.0 - (-settings.follow_speed * time.delta_seconds()).exp(),
+        )
+    };
+    if eased != current {
+        camera_transform.translation = eased.extend(camera_transform.translation.z);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{thread, time::Duration};
+
+use bevy::prelude::*;
+use roguelike::{
+    actor::Player,
+    camera_controls::{pan_camera_to, CameraMode, CameraSettings, MainCamera},
+    key_bindings::{KeyBindings, Preset},
+    position::Position,
+    view::follow_player,
+};
+
+//Just the camera following a player standing at (5, 5)
+fn camera_app() -> (App, Entity) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .init_resource::<Input<KeyCode>>()
+        .insert_resource(KeyBindings::preset(Preset::Wasd))
+        .init_resource::<CameraSettings>()
+        .init_resource::<CameraMode>()
+        .add_systems(Update, follow_player);
+    app.world.spawn((Player, Position::new(5, 5)));
+    let camera = app.world.spawn((MainCamera, Transform::default())).id();
+    app.update();
+    (app, camera)
+}
+
+fn camera_position(app: &App, camera: Entity) -> Vec2 {
+    app.world
+        .get::<Transform>(camera)
+        .unwrap()
+        .translation
+        .truncate()
+}
+
+#[test]
+fn the_camera_follows_the_player() {
+    let (app, camera) = camera_app();
+    assert_eq!(camera_position(&app, camera), Vec2::new(60.0, 60.0));
+}
+
+#[test]
+fn the_camera_stays_where_the_minimap_panned_it() {
+    let (mut app, camera) = camera_app();
+    let target = Vec2::new(300.0, 240.0);
+    app.world
+        .resource_scope(|world, mut mode: Mut<CameraMode>| {
+            let mut transform = world.get_mut::<Transform>(camera).unwrap();
+            pan_camera_to(target, &mut transform, &mut mode);
+        });
+
+    for _ in 0..5 {
+        //so following would have had time to ease the camera back
+        thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+
+    assert_eq!(camera_position(&app, camera), target);
+    assert_eq!(*app.world.resource::<CameraMode>(), CameraMode::FreeLook);
+}
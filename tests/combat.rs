@@ -0,0 +1,156 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{act, in_scratch_directory, player, player_position, quiet_run, update_until};
+use roguelike::{
+    actor::{bat_bundle, Actor, CombatStats, Dormant, Enemy, TurnState},
+    combat::{AttackEvent, AttackKind, Dead},
+    game_state::AppState,
+    level_generation::map::Map,
+    message_log::MessageLog,
+    player_action::{PlayerAction, PlayerInput},
+    position::PositionDelta,
+};
+
+//The steps from the player onto free floor next to them
+fn open_steps(app: &mut App) -> Vec<PositionDelta> {
+    let position = player_position(app);
+    let map = app.world.resource::<Map>();
+    [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+    ]
+    .into_iter()
+    .map(|(x, y)| PositionDelta::new(x, y))
+    .filter(|delta| {
+        let step = position + *delta;
+        map.get(step.x, step.y).is_some_and(|tile| tile.passable)
+    })
+    .collect()
+}
+
+//An awake bat on the given step from the player
+fn spawn_bat(app: &mut App, delta: PositionDelta) -> Entity {
+    let position = player_position(app) + delta;
+    let mut bat = app.world.spawn(bat_bundle(position));
+    bat.remove::<Dormant>();
+    bat.id()
+}
+
+fn logged(app: &App, text: &str) -> bool {
+    let messages = &app.world.resource::<MessageLog>().messages;
+    messages.iter().any(|message| message.text == text)
+}
+
+fn health(app: &App, entity: Entity) -> f32 {
+    app.world.get::<Actor>(entity).unwrap().health
+}
+
+#[test]
+fn melee_damage_is_attack_less_defense() {
+    let mut app = quiet_run(3);
+    let step = open_steps(&mut app)[0];
+    let bat = spawn_bat(&mut app, step);
+    app.world.get_mut::<CombatStats>(bat).unwrap().defense = 2.;
+
+    act(&mut app, PlayerAction::Move(step));
+
+    //the player hits for 5
+    assert_eq!(health(&app, bat), 7.);
+    assert!(logged(&app, "You hit the bat for 3."));
+}
+
+#[test]
+fn every_hit_does_at_least_one_damage() {
+    let mut app = quiet_run(3);
+    let step = open_steps(&mut app)[0];
+    let bat = spawn_bat(&mut app, step);
+    app.world.get_mut::<CombatStats>(bat).unwrap().defense = 50.;
+
+    act(&mut app, PlayerAction::Move(step));
+
+    assert_eq!(health(&app, bat), 9.);
+}
+
+#[test]
+fn killed_enemies_are_despawned() {
+    let mut app = quiet_run(3);
+    let step = open_steps(&mut app)[0];
+    let bat = spawn_bat(&mut app, step);
+    app.world.get_mut::<Actor>(bat).unwrap().health = 3.;
+
+    act(&mut app, PlayerAction::Move(step));
+
+    assert!(app.world.get_entity(bat).is_none());
+    assert!(logged(&app, "The bat dies."));
+    assert_eq!(
+        *app.world.resource::<State<TurnState>>().get(),
+        TurnState::Player
+    );
+}
+
+#[test]
+fn a_dead_player_ends_the_game() {
+    //the end of the run writes out its replay
+    in_scratch_directory(|| {
+        let mut app = quiet_run(3);
+        let step = open_steps(&mut app)[0];
+        let bat = spawn_bat(&mut app, step);
+        let player = player(&mut app);
+        app.world.get_mut::<Actor>(player).unwrap().health = 1.;
+
+        //the bat is next to the player, so it attacks on its turn
+        act(&mut app, PlayerAction::Wait);
+        update_until(&mut app, |app| {
+            *app.world.resource::<State<AppState>>().get() == AppState::GameOver
+        });
+
+        assert!(health(&app, player) <= 0.);
+        assert!(app.world.get::<Dead>(player).is_some());
+        assert!(app.world.get_entity(bat).is_some());
+        assert!(logged(&app, "You die..."));
+    });
+}
+
+#[test]
+fn enemies_killed_during_the_enemy_phase_are_skipped() {
+    let mut app = quiet_run(3);
+    let steps = open_steps(&mut app);
+    let doomed = spawn_bat(&mut app, steps[0]);
+    let survivor = spawn_bat(&mut app, steps[1]);
+    let player = player(&mut app);
+
+    app.world.send_event(PlayerInput(PlayerAction::Wait));
+    app.update();
+    //killed in the frame the enemies start taking their turns, before the despawn is applied
+    app.world.send_event(AttackEvent {
+        attacker: player,
+        defender: doomed,
+        kind: AttackKind::Magic(100.),
+    });
+    app.update();
+    assert_eq!(
+        *app.world.resource::<State<TurnState>>().get(),
+        TurnState::Enemy
+    );
+    update_until(&mut app, |app| {
+        *app.world.resource::<State<TurnState>>().get() == TurnState::Player
+    });
+
+    assert!(app.world.get_entity(doomed).is_none());
+    assert!(app.world.get_entity(survivor).is_some());
+    //only the survivor got to attack
+    assert_eq!(health(&app, player), 98.);
+    let enemies = app
+        .world
+        .query_filtered::<Entity, With<Enemy>>()
+        .iter(&app.world)
+        .count();
+    assert_eq!(enemies, 1);
+}
@@ -0,0 +1,110 @@
+//Helpers shared by the tests that run the simulation headless. Not every test file uses all of them.
+#![allow(dead_code)]
+
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use roguelike::{
+    actor::{Enemy, Player, TurnState},
+    game_state::{AppState, NextRunSeed},
+    item::{Item, ItemDefinitions},
+    player_action::{PlayerAction, PlayerInput},
+    position::Position,
+    SimulationPlugins,
+};
+
+//Saves and replays are written to the working directory, so tests that write them take turns in
+//a directory of their own rather than leaving files in the crate
+static SCRATCH_DIRECTORY: Mutex<()> = Mutex::new(());
+
+pub fn in_scratch_directory(test: impl FnOnce()) {
+    let _guard = SCRATCH_DIRECTORY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let directory = std::env::temp_dir().join(format!("roguelike-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::env::set_current_dir(&directory).unwrap();
+    test();
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, SimulationPlugins));
+    app
+}
+
+pub fn start_run(app: &mut App) {
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::NewGame);
+    for _ in 0..5 {
+        app.update();
+    }
+}
+
+//A run on a fixed seed, with the player alone on the level
+pub fn quiet_run(seed: u64) -> App {
+    let mut app = headless_app();
+    app.insert_resource(NextRunSeed(seed));
+    start_run(&mut app);
+    let enemies: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Enemy>>()
+        .iter(&app.world)
+        .collect();
+    for enemy in enemies {
+        app.world.entity_mut(enemy).despawn_recursive();
+    }
+    app
+}
+
+pub fn player(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, With<Player>>()
+        .single(&app.world)
+}
+
+pub fn player_position(app: &mut App) -> Position {
+    *app.world
+        .query_filtered::<&Position, With<Player>>()
+        .single(&app.world)
+}
+
+//Sends an action once it's the player's turn, and gives the turn it starts time to play out
+pub fn act(app: &mut App, action: PlayerAction) {
+    update_until(app, |app| {
+        *app.world.resource::<State<TurnState>>().get() == TurnState::Player
+    });
+    app.world.send_event(PlayerInput(action));
+    for _ in 0..4 {
+        app.update();
+    }
+}
+
+//Runs frames until the condition holds, for things that take a varying number of frames to settle
+pub fn update_until(app: &mut App, condition: impl Fn(&App) -> bool) {
+    for _ in 0..20 {
+        if condition(app) {
+            return;
+        }
+        app.update();
+    }
+    panic!("the condition never held");
+}
+
+//Spawns some of the named item, on the floor at the position or nowhere if there isn't one
+pub fn spawn_item(app: &mut App, name: &str, count: u32, position: Option<Position>) -> Entity {
+    let definitions = app.world.resource::<ItemDefinitions>();
+    let kind = definitions.find(name).unwrap();
+    let item = Item {
+        kind,
+        count,
+        charges: definitions[kind].charges,
+    };
+    let mut entity = app.world.spawn(item);
+    if let Some(position) = position {
+        entity.insert(position);
+    }
+    entity.id()
+}
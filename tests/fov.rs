@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use roguelike::{
+    fov::{compute_fov, FovAlgorithm},
+    level_generation::map::Map,
+};
+
+fn random_map(rng: &mut StdRng, width: usize, height: usize, wall_chance: f64) -> Map {
+    let mut map = Map::filled(width, height);
+    for x in 0..width {
+        for y in 0..height {
+            map.get_mut(x, y).unwrap().passable = !rng.gen_bool(wall_chance);
+        }
+    }
+    map
+}
+
+fn open_map(width: usize, height: usize) -> Map {
+    let mut map = Map::filled(width, height);
+    for x in 0..width {
+        for y in 0..height {
+            map.get_mut(x, y).unwrap().passable = true;
+        }
+    }
+    map
+}
+
+fn random_tile(rng: &mut StdRng, map: &Map) -> (usize, usize) {
+    (rng.gen_range(0..map.width), rng.gen_range(0..map.height))
+}
+
+fn assert_symmetric(algorithm: FovAlgorithm) {
+    let radius = 8;
+    for seed in 0..40 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let map = random_map(&mut rng, 24, 24, 0.3);
+        let mut cache: HashMap<(usize, usize), HashSet<(usize, usize)>> = HashMap::new();
+        let mut fov = |origin| {
+            cache
+                .entry(origin)
+                .or_insert_with(|| compute_fov(&map, origin, radius, algorithm))
+                .clone()
+        };
+
+        for _ in 0..200 {
+            let a = random_tile(&mut rng, &map);
+            let b = random_tile(&mut rng, &map);
+            if !map.get(a.0, a.1).unwrap().passable || !map.get(b.0, b.1).unwrap().passable {
+                continue;
+            }
+            let a_sees_b = fov(a).contains(&b);
+            let b_sees_a = fov(b).contains(&a);
+            assert_eq!(
+                a_sees_b, b_sees_a,
+                "{algorithm:?} is asymmetric between {a:?} and {b:?} (seed {seed})"
+            );
+        }
+    }
+}
+
+#[test]
+fn symmetric_shadowcasting_is_symmetric() {
+    assert_symmetric(FovAlgorithm::SymmetricShadowcasting);
+}
+
+#[test]
+fn permissive_is_symmetric() {
+    assert_symmetric(FovAlgorithm::Permissive);
+}
+
+#[test]
+fn viewer_always_sees_its_own_tile() {
+    for algorithm in FovAlgorithm::ALL {
+        for seed in 0..40 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let map = random_map(&mut rng, 20, 20, 0.5);
+            let origin = random_tile(&mut rng, &map);
+            for radius in [0, 1, 6] {
+                assert!(
+                    compute_fov(&map, origin, radius, algorithm).contains(&origin),
+                    "{algorithm:?} hides the viewer's tile at {origin:?} (seed {seed})"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn visible_tiles_are_on_the_map_and_within_radius() {
+    for algorithm in FovAlgorithm::ALL {
+        for seed in 0..40 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let map = random_map(&mut rng, 16, 12, 0.25);
+            let origin = random_tile(&mut rng, &map);
+            let radius = rng.gen_range(1..10);
+            for (x, y) in compute_fov(&map, origin, radius, algorithm) {
+                assert!(map.get(x, y).is_some(), "{algorithm:?} saw ({x}, {y})");
+                let (dx, dy) = (x.abs_diff(origin.0), y.abs_diff(origin.1));
+                assert!(
+                    dx * dx + dy * dy <= radius * radius,
+                    "{algorithm:?} saw ({x}, {y}) outside radius {radius} of {origin:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn map_edges_are_visible() {
+    let map = open_map(12, 10);
+    for algorithm in FovAlgorithm::ALL {
+        for origin in [(0, 0), (11, 9), (0, 9), (11, 0), (5, 0), (0, 4)] {
+            let visible = compute_fov(&map, origin, 20, algorithm);
+            for x in 0..map.width {
+                for y in 0..map.height {
+                    assert!(
+                        visible.contains(&(x, y)),
+                        "{algorithm:?} from {origin:?} can't see ({x}, {y}) on an open map"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn walls_are_visible_but_block_sight() {
+    let mut map = open_map(15, 15);
+    for y in 0..map.height {
+        map.get_mut(7, y).unwrap().passable = false;
+    }
+    for algorithm in FovAlgorithm::ALL {
+        let visible = compute_fov(&map, (3, 7), 10, algorithm);
+        assert!(
+            visible.contains(&(7, 7)),
+            "{algorithm:?} can't see the wall"
+        );
+        assert!(
+            visible.iter().all(|(x, _)| *x <= 7),
+            "{algorithm:?} sees through the wall"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{act, player, player_position, quiet_run, spawn_item};
+use roguelike::{
+    actor::Actor,
+    fov::Spotted,
+    item::{Inventory, InventoryAction, Item, ItemDefinitions},
+    level_generation::map::{Map, ViewStatus},
+    player_action::PlayerAction,
+    position::Position,
+    status::{StatusEffects, StatusKind},
+};
+
+//Counts of the named item in each stack the player carries, in inventory order
+fn carried(app: &mut App, name: &str) -> Vec<u32> {
+    let player = player(app);
+    let kind = app.world.resource::<ItemDefinitions>().find(name).unwrap();
+    let items = app.world.get::<Inventory>(player).unwrap().items.clone();
+    items
+        .iter()
+        .map(|entity| *app.world.get::<Item>(*entity).unwrap())
+        .filter(|item| item.kind == kind)
+        .map(|item| item.count)
+        .collect()
+}
+
+fn give(app: &mut App, name: &str, count: u32) {
+    let item = spawn_item(app, name, count, None);
+    let player = player(app);
+    app.world
+        .get_mut::<Inventory>(player)
+        .unwrap()
+        .items
+        .push(item);
+}
+
+fn health(app: &mut App) -> f32 {
+    let player = player(app);
+    app.world.get::<Actor>(player).unwrap().health
+}
+
+#[test]
+fn picking_up_takes_the_item_off_the_floor() {
+    let mut app = quiet_run(3);
+    let position = player_position(&mut app);
+    let dagger = spawn_item(&mut app, "dagger", 1, Some(position));
+
+    act(&mut app, PlayerAction::PickUp);
+
+    assert_eq!(carried(&mut app, "dagger"), vec![1]);
+    assert!(app.world.get::<Position>(dagger).is_none());
+}
+
+#[test]
+fn picked_up_items_top_up_partial_stacks() {
+    let mut app = quiet_run(3);
+    let position = player_position(&mut app);
+    spawn_item(&mut app, "arrow", 20, Some(position));
+    act(&mut app, PlayerAction::PickUp);
+    assert_eq!(carried(&mut app, "arrow"), vec![20]);
+
+    //arrows stack to 30, so the rest start a new stack
+    spawn_item(&mut app, "arrow", 15, Some(position));
+    act(&mut app, PlayerAction::PickUp);
+    assert_eq!(carried(&mut app, "arrow"), vec![30, 5]);
+}
+
+#[test]
+fn a_full_inventory_leaves_items_on_the_floor() {
+    let mut app = quiet_run(3);
+    let player = player(&mut app);
+    app.world.get_mut::<Inventory>(player).unwrap().capacity = 0;
+    let position = player_position(&mut app);
+    let dagger = spawn_item(&mut app, "dagger", 1, Some(position));
+
+    act(&mut app, PlayerAction::PickUp);
+
+    assert!(carried(&mut app, "dagger").is_empty());
+    assert_eq!(app.world.get::<Position>(dagger), Some(&position));
+}
+
+#[test]
+fn using_a_potion_heals_and_uses_one_up() {
+    let mut app = quiet_run(3);
+    let player = player(&mut app);
+    app.world.get_mut::<Actor>(player).unwrap().health = 50.;
+    give(&mut app, "health potion", 2);
+
+    act(
+        &mut app,
+        PlayerAction::Inventory(InventoryAction::Use(0, None)),
+    );
+
+    assert_eq!(health(&mut app), 75.);
+    assert_eq!(carried(&mut app, "health potion"), vec![1]);
+}
+
+#[test]
+fn status_potions_apply_their_status() {
+    let mut app = quiet_run(3);
+    give(&mut app, "potion of haste", 1);
+
+    act(
+        &mut app,
+        PlayerAction::Inventory(InventoryAction::Use(0, None)),
+    );
+
+    let player = player(&mut app);
+    let status_effects = app.world.get::<StatusEffects>(player).unwrap();
+    assert!(status_effects.has(StatusKind::Hasted));
+    assert!(carried(&mut app, "potion of haste").is_empty());
+}
+
+#[test]
+fn a_potion_of_poison_poisons_the_drinker() {
+    let mut app = quiet_run(3);
+    give(&mut app, "potion of poison", 1);
+
+    act(
+        &mut app,
+        PlayerAction::Inventory(InventoryAction::Use(0, None)),
+    );
+
+    let player = player(&mut app);
+    let status_effects = app.world.get::<StatusEffects>(player).unwrap();
+    assert!(status_effects.has(StatusKind::Poisoned));
+}
+
+#[test]
+fn magic_mapping_reveals_the_whole_map() {
+    let mut app = quiet_run(3);
+    give(&mut app, "scroll of magic mapping", 1);
+
+    act(
+        &mut app,
+        PlayerAction::Inventory(InventoryAction::Use(0, None)),
+    );
+
+    let map = app.world.resource::<Map>();
+    assert!((0..map.width)
+        .flat_map(|x| (0..map.height).map(move |y| (x, y)))
+        .all(|(x, y)| map.get(x, y).unwrap().view_status != ViewStatus::Unexplored));
+}
+
+#[test]
+fn magic_mapping_doesnt_reveal_the_items_lying_about() {
+    let mut app = quiet_run(3);
+    let map = app.world.resource::<Map>();
+    let unexplored = (0..map.width)
+        .flat_map(|x| (0..map.height).map(move |y| (x, y)))
+        .find(|(x, y)| {
+            let tile = map.get(*x, *y).unwrap();
+            tile.passable && tile.view_status == ViewStatus::Unexplored
+        })
+        .map(|(x, y)| Position::new(x, y))
+        .unwrap();
+    let hidden = spawn_item(&mut app, "arrow", 3, Some(unexplored));
+    let underfoot = player_position(&mut app);
+    let seen = spawn_item(&mut app, "arrow", 3, Some(underfoot));
+    give(&mut app, "scroll of magic mapping", 1);
+
+    act(
+        &mut app,
+        PlayerAction::Inventory(InventoryAction::Use(0, None)),
+    );
+
+    let map = app.world.resource::<Map>();
+    assert!(map.get(unexplored.x, unexplored.y).unwrap().view_status == ViewStatus::Revealed);
+    assert!(app.world.get::<Spotted>(hidden).is_none());
+    assert!(app.world.get::<Spotted>(seen).is_some());
+}
+
+#[test]
+fn item_definitions_parse_from_the_data_file() {
+    let contents = std::fs::read_to_string("assets/items.ron").unwrap();
+    let definitions = ItemDefinitions::parse(&contents).unwrap();
+    assert!(definitions.find("health potion").is_some());
+    assert!(ItemDefinitions::parse("[]").is_err());
+    assert!(ItemDefinitions::parse("not ron").is_err());
+}
+
+#[test]
+fn item_definitions_that_cant_be_spawned_are_refused() {
+    let never_spawned = r#"[(name: "rock", sprite_index: 0, spawn_weight: 0)]"#;
+    assert!(ItemDefinitions::parse(never_spawned).is_err());
+    let empty_stacks = r#"[(name: "rock", sprite_index: 0, max_stack: 0, spawn_weight: 1)]"#;
+    assert!(ItemDefinitions::parse(empty_stacks).is_err());
+}
@@ -0,0 +1,27 @@
+use roguelike::key_bindings::{KeyAction, KeyBindings, Preset};
+
+#[test]
+fn hints_name_the_bound_keys() {
+    let bindings = KeyBindings::preset(Preset::Wasd);
+    assert_eq!(
+        bindings.describe(&[KeyAction::Look, KeyAction::Cancel]),
+        "L/Escape"
+    );
+    assert_eq!(bindings.describe(&KeyAction::MOVES), "W/E/D/C/S/Z/A/Q");
+
+    let bindings = KeyBindings::preset(Preset::Vi);
+    assert_eq!(
+        bindings.describe(&[KeyAction::Look, KeyAction::Cancel]),
+        "Semicolon/Escape"
+    );
+}
+
+#[test]
+fn every_preset_can_back_out_and_target() {
+    for preset in [Preset::Wasd, Preset::Numpad, Preset::Vi, Preset::Arrows] {
+        let bindings = KeyBindings::preset(preset);
+        for action in [KeyAction::Cancel, KeyAction::NextTarget, KeyAction::Confirm] {
+            assert!(!bindings.keys(action).is_empty(), "{preset:?} {action:?}");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{act, player, player_position, quiet_run};
+use roguelike::{
+    effect::{Effect, EffectEvent},
+    level_generation::map::Map,
+    lighting::{update_light_map, Fire, LightMap, LightSource},
+    player_action::PlayerAction,
+    position::Position,
+    status::{StatusEffects, StatusKind},
+};
+
+//Just the light map, over an open map with the given tiles walled off
+fn lighting_app(walls: &[(usize, usize)]) -> App {
+    let mut map = Map::filled(20, 20);
+    for x in 0..20 {
+        for y in 0..20 {
+            map.get_mut(x, y).unwrap().passable = !walls.contains(&(x, y));
+        }
+    }
+    let mut app = App::new();
+    app.insert_resource(map)
+        .init_resource::<LightMap>()
+        .add_systems(Update, update_light_map);
+    app
+}
+
+fn brightness(app: &App, x: usize, y: usize) -> f32 {
+    app.world.resource::<LightMap>().get(x, y).max_element()
+}
+
+#[test]
+fn light_fades_with_distance() {
+    let mut app = lighting_app(&[]);
+    app.world.spawn((LightSource::TORCH, Position::new(10, 10)));
+    app.update();
+
+    let along: Vec<f32> = (10..=17).map(|x| brightness(&app, x, 10)).collect();
+    assert!(along.windows(2).all(|pair| pair[0] > pair[1]));
+    assert!(app.world.resource::<LightMap>().is_lit(10, 10));
+    //beyond the radius of 7
+    assert_eq!(brightness(&app, 18, 10), 0.);
+}
+
+#[test]
+fn walls_cast_shadows() {
+    let walls: Vec<(usize, usize)> = (5..15).map(|y| (12, y)).collect();
+    let mut app = lighting_app(&walls);
+    app.world.spawn((LightSource::TORCH, Position::new(10, 10)));
+    app.update();
+
+    //the wall itself is lit, but nothing behind it
+    assert!(brightness(&app, 12, 10) > 0.);
+    assert_eq!(brightness(&app, 13, 10), 0.);
+    assert_eq!(brightness(&app, 14, 10), 0.);
+    assert!(brightness(&app, 10, 13) > 0.);
+}
+
+#[test]
+fn burning_actors_give_off_firelight() {
+    let mut app = lighting_app(&[]);
+    let mut status_effects = StatusEffects::default();
+    status_effects.apply(StatusKind::Burning, 2);
+    let actor = app.world.spawn((status_effects, Position::new(5, 5))).id();
+    app.update();
+
+    let light = app.world.resource::<LightMap>().get(5, 5);
+    assert!(light.x > light.z);
+
+    //the fire goes out with the status
+    app.world
+        .get_mut::<StatusEffects>(actor)
+        .unwrap()
+        .effects
+        .clear();
+    app.update();
+    assert_eq!(brightness(&app, 5, 5), 0.);
+}
+
+#[test]
+fn burning_tiles_give_off_firelight_until_they_burn_out() {
+    let mut app = quiet_run(3);
+    let player = player(&mut app);
+    let position = player_position(&mut app);
+    app.world.send_event(EffectEvent {
+        effects: vec![Effect::Ignite(2)],
+        source: player,
+        target: None,
+    });
+    app.update();
+    app.update();
+    let light = app
+        .world
+        .resource::<LightMap>()
+        .get(position.x + 1, position.y);
+    assert!(light.x > light.z);
+
+    //standing in the fire sets the player alight
+    act(&mut app, PlayerAction::Wait);
+    let status_effects = app.world.get::<StatusEffects>(player).unwrap();
+    assert!(status_effects.has(StatusKind::Burning));
+
+    act(&mut app, PlayerAction::Wait);
+    let fires = app
+        .world
+        .query_filtered::<(), With<Fire>>()
+        .iter(&app.world)
+        .count();
+    assert_eq!(fires, 0);
+}
+
+#[derive(Resource, Default)]
+struct Recalculations(u32);
+
+fn count_recalculations(light_map: Res<LightMap>, mut recalculations: ResMut<Recalculations>) {
+    if light_map.is_changed() {
+        recalculations.0 += 1;
+    }
+}
+
+#[test]
+fn unchanged_lights_leave_the_light_map_alone() {
+    let mut app = lighting_app(&[]);
+    app.init_resource::<Recalculations>()
+        .add_systems(Update, count_recalculations.after(update_light_map));
+    let torch = app
+        .world
+        .spawn((
+            LightSource::TORCH,
+            Position::new(10, 10),
+            StatusEffects::default(),
+        ))
+        .id();
+    app.update();
+    assert_eq!(app.world.resource::<Recalculations>().0, 1);
+
+    //status effects that give off no light aren't enough to recalculate
+    app.world
+        .get_mut::<StatusEffects>(torch)
+        .unwrap()
+        .apply(StatusKind::Hasted, 3);
+    app.update();
+    assert_eq!(app.world.resource::<Recalculations>().0, 1);
+
+    app.world.get_mut::<Position>(torch).unwrap().x = 11;
+    app.update();
+    assert_eq!(app.world.resource::<Recalculations>().0, 2);
+}
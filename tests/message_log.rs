@@ -0,0 +1,16 @@
+use roguelike::message_log::{MessageKind, MessageLog, MESSAGE_LOG_CAPACITY};
+
+#[test]
+fn the_oldest_messages_are_dropped_once_the_log_is_full() {
+    let mut message_log = MessageLog::default();
+    for turn in 0..MESSAGE_LOG_CAPACITY + 10 {
+        message_log.push(MessageKind::Info, format!("Turn {turn}."));
+    }
+
+    assert_eq!(message_log.messages.len(), MESSAGE_LOG_CAPACITY);
+    assert_eq!(message_log.messages.front().unwrap().text, "Turn 10.");
+    assert_eq!(
+        message_log.messages.back().unwrap().text,
+        format!("Turn {}.", MESSAGE_LOG_CAPACITY + 9)
+    );
+}
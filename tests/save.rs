@@ -0,0 +1,187 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{
+    act, headless_app, in_scratch_directory, player, player_position, spawn_item, start_run,
+    update_until,
+};
+use roguelike::{
+    actor::{Actor, Dormant, Enemy, EnemyKind, Player, TurnCount, TurnState},
+    fov::{ghost_bundle, Ghost, Spotted},
+    game_state::{AppState, NextRunSeed},
+    item::{Equipment, Inventory, InventoryAction, Item, ItemDefinitions},
+    level_generation::map::Map,
+    player_action::PlayerAction,
+    position::{Position, PositionDelta},
+    rng::GameRng,
+    save::{SaveGame, SaveSlot, SAVE_VERSION},
+};
+
+fn item_name(app: &App, entity: Entity) -> String {
+    let item = app.world.get::<Item>(entity).unwrap();
+    let definitions = app.world.resource::<ItemDefinitions>();
+    format!("{} x{}", definitions[item.kind].name, item.count)
+}
+
+//Everything a save is meant to keep
+fn snapshot(app: &mut App) -> Vec<String> {
+    let player = player(app);
+    let inventory = app.world.get::<Inventory>(player).unwrap().items.clone();
+    let equipment = app.world.get::<Equipment>(player).unwrap();
+    let (weapon, armour) = (equipment.weapon, equipment.armour);
+    let mut enemies: Vec<String> = app
+        .world
+        .query::<(&EnemyKind, &Position, &Actor, Option<&Dormant>)>()
+        .iter(&app.world)
+        .map(|(kind, position, actor, dormant)| {
+            format!(
+                "{kind:?} {position:?} {} {}",
+                actor.health,
+                dormant.is_some()
+            )
+        })
+        .collect();
+    enemies.sort();
+    let mut ghosts: Vec<String> = app
+        .world
+        .query_filtered::<&Position, With<Ghost>>()
+        .iter(&app.world)
+        .map(|position| format!("{position:?}"))
+        .collect();
+    ghosts.sort();
+    let mut spotted: Vec<String> = app
+        .world
+        .query_filtered::<&Position, (With<Item>, With<Spotted>)>()
+        .iter(&app.world)
+        .map(|position| format!("{position:?}"))
+        .collect();
+    spotted.sort();
+
+    vec![
+        ron::to_string(app.world.resource::<Map>()).unwrap(),
+        format!(
+            "player at {:?} with {} health",
+            app.world.get::<Position>(player).unwrap(),
+            app.world.get::<Actor>(player).unwrap().health
+        ),
+        format!(
+            "carrying {:?}",
+            inventory
+                .iter()
+                .map(|entity| item_name(app, *entity))
+                .collect::<Vec<_>>()
+        ),
+        format!(
+            "wielding {:?} wearing {:?}",
+            weapon.map(|entity| item_name(app, entity)),
+            armour.map(|entity| item_name(app, entity))
+        ),
+        format!("enemies {enemies:?}"),
+        format!("ghosts {ghosts:?}"),
+        format!("spotted items {spotted:?}"),
+        ron::to_string(app.world.resource::<GameRng>()).unwrap(),
+        format!("turn {}", app.world.resource::<TurnCount>().0),
+    ]
+}
+
+fn load(app: &mut App) {
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::LoadingSave);
+    app.update();
+    update_until(app, |app| {
+        *app.world.resource::<State<AppState>>().get() != AppState::LoadingSave
+    });
+}
+
+#[test]
+fn a_loaded_game_carries_on_where_it_was_saved() {
+    in_scratch_directory(|| {
+        let mut app = headless_app();
+        app.insert_resource(NextRunSeed(3));
+        start_run(&mut app);
+
+        let player = player(&mut app);
+        for (name, count) in [("arrow", 12), ("dagger", 1), ("leather armour", 1)] {
+            let item = spawn_item(&mut app, name, count, None);
+            app.world
+                .get_mut::<Inventory>(player)
+                .unwrap()
+                .items
+                .push(item);
+        }
+        let underfoot = player_position(&mut app);
+        spawn_item(&mut app, "health potion", 1, Some(underfoot));
+        act(&mut app, PlayerAction::Inventory(InventoryAction::Equip(1)));
+        act(&mut app, PlayerAction::Inventory(InventoryAction::Equip(2)));
+        for (x, y) in [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, -1)] {
+            act(&mut app, PlayerAction::Move(PositionDelta::new(x, y)));
+        }
+        //where the player last saw the furthest bat, as if it had since flown off
+        let (bat, position) = app
+            .world
+            .query_filtered::<(Entity, &Position), With<Enemy>>()
+            .iter(&app.world)
+            .max_by_key(|(_, position)| position.x)
+            .map(|(bat, position)| (bat, *position + PositionDelta::new(-1, 0)))
+            .unwrap();
+        app.world
+            .spawn(ghost_bundle(0, Name::new("bat"), position, bat));
+
+        //saving is only offered on the player's turn
+        update_until(&mut app, |app| {
+            *app.world.resource::<State<TurnState>>().get() == TurnState::Player
+        });
+        let saved = snapshot(&mut app);
+        assert_ne!(saved[5], "ghosts []");
+        assert_ne!(saved[6], "spotted items []");
+        assert_eq!(
+            saved[3],
+            "wielding Some(\"dagger x1\") wearing Some(\"leather armour x1\")"
+        );
+        app.world.send_event(SaveGame);
+        app.update();
+        assert!(app.world.resource::<SaveSlot>().exists);
+
+        //play on, so there's something for the load to undo
+        for _ in 0..5 {
+            act(&mut app, PlayerAction::Wait);
+        }
+        assert_ne!(snapshot(&mut app), saved);
+
+        load(&mut app);
+        assert_eq!(app.world.resource::<SaveSlot>().error, None);
+        assert_eq!(
+            *app.world.resource::<State<AppState>>().get(),
+            AppState::Playing
+        );
+        let loaded = snapshot(&mut app);
+        for (loaded, saved) in loaded.iter().zip(&saved) {
+            assert_eq!(loaded, saved);
+        }
+    });
+}
+
+#[test]
+fn saves_from_another_version_are_refused() {
+    in_scratch_directory(|| {
+        std::fs::write("savegame.ron", format!("(version: {})", SAVE_VERSION + 1)).unwrap();
+        let mut app = headless_app();
+        app.update();
+
+        load(&mut app);
+
+        assert_eq!(
+            *app.world.resource::<State<AppState>>().get(),
+            AppState::MainMenu
+        );
+        let error = app.world.resource::<SaveSlot>().error.clone().unwrap();
+        assert!(error.contains(&format!("version {}", SAVE_VERSION + 1)));
+        assert!(app
+            .world
+            .query_filtered::<(), With<Player>>()
+            .iter(&app.world)
+            .next()
+            .is_none());
+    });
+}
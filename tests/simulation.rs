@@ -0,0 +1,204 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{headless_app, player_position, start_run};
+use roguelike::{
+    actor::{Actor, Enemy, Player, TurnCount, TurnState},
+    auto_explore::AutoExplore,
+    bot::{BotPlugin, BotStats},
+    game_state::{AppState, NextRunSeed},
+    item::Inventory,
+    level_generation::map::Map,
+    message_log::MessageLog,
+    player_action::{PlayerAction, PlayerInput},
+    position::{Position, PositionDelta},
+    replay::{Replay, ReplayPlayback},
+    rest::Rest,
+    travel::Travel,
+};
+
+//Everything the player's actions can affect, to compare two runs by
+fn snapshot(app: &mut App) -> String {
+    let turn = app.world.resource::<TurnCount>().0;
+    let position = player_position(app);
+    let carried = app
+        .world
+        .query_filtered::<&Inventory, With<Player>>()
+        .single(&app.world)
+        .items
+        .len();
+    let mut enemies: Vec<(usize, usize)> = app
+        .world
+        .query_filtered::<&Position, With<Enemy>>()
+        .iter(&app.world)
+        .map(|position| (position.x, position.y))
+        .collect();
+    enemies.sort();
+    format!("turn {turn} at {position:?} carrying {carried} enemies {enemies:?}")
+}
+
+//Tries each direction in turn, waiting for the enemies to move in between
+fn walk(app: &mut App, steps: usize) {
+    let deltas = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, -1)];
+    for step in 0..steps {
+        let (x, y) = deltas[step % deltas.len()];
+        app.world
+            .send_event(PlayerInput(PlayerAction::Move(PositionDelta::new(x, y))));
+        app.world.send_event(PlayerInput(PlayerAction::PickUp));
+        for _ in 0..4 {
+            app.update();
+        }
+    }
+}
+
+#[test]
+fn runs_without_rendering() {
+    let mut app = headless_app();
+    start_run(&mut app);
+
+    assert_eq!(
+        *app.world.resource::<State<AppState>>().get(),
+        AppState::Playing
+    );
+    assert!(app.world.contains_resource::<Map>());
+    assert_eq!(
+        *app.world.resource::<State<TurnState>>().get(),
+        TurnState::Player
+    );
+
+    let start = player_position(&mut app);
+    let turns = app.world.resource::<TurnCount>().0;
+    walk(&mut app, 20);
+    assert!(app.world.resource::<TurnCount>().0 > turns);
+    assert_ne!(player_position(&mut app), start);
+}
+
+#[test]
+fn replay_reproduces_the_run() {
+    let mut recorded = headless_app();
+    start_run(&mut recorded);
+    walk(&mut recorded, 60);
+    //lets the last enemy phase finish
+    for _ in 0..100 {
+        recorded.update();
+    }
+    let expected = snapshot(&mut recorded);
+    let replay = recorded.world.resource::<Replay>().clone();
+    assert!(!replay.actions.is_empty());
+
+    let mut replayed = headless_app();
+    //entering the main menu ends any playback, so wait until the game is sitting in it
+    replayed.update();
+    replayed.insert_resource(ReplayPlayback::from(replay));
+    start_run(&mut replayed);
+    for _ in 0..1000 {
+        if !replayed.world.contains_resource::<ReplayPlayback>() {
+            break;
+        }
+        replayed.update();
+    }
+    assert!(!replayed.world.contains_resource::<ReplayPlayback>());
+    for _ in 0..100 {
+        replayed.update();
+    }
+    assert_eq!(snapshot(&mut replayed), expected);
+}
+
+#[test]
+fn bot_plays_on_its_own() {
+    let mut app = headless_app();
+    app.add_plugins(BotPlugin).insert_resource(NextRunSeed(1));
+    start_run(&mut app);
+    let start = player_position(&mut app);
+    for _ in 0..500 {
+        app.update();
+    }
+
+    let stats = app.world.resource::<BotStats>();
+    assert!(!stats.stuck);
+    assert!(stats.actions > 0);
+    //it fights its way through, rather than only wandering
+    assert!(stats.kills > 0);
+    assert!(stats.pickups > 0);
+    assert!(app.world.resource::<TurnCount>().0 > 10);
+    assert_ne!(player_position(&mut app), start);
+}
+
+#[test]
+fn auto_explore_stops_on_its_own() {
+    let mut app = headless_app();
+    app.insert_resource(NextRunSeed(2));
+    start_run(&mut app);
+    let start = player_position(&mut app);
+    app.world.resource_mut::<AutoExplore>().start();
+    for _ in 0..5000 {
+        if !app.world.resource::<AutoExplore>().active {
+            break;
+        }
+        app.update();
+    }
+
+    assert!(!app.world.resource::<AutoExplore>().active);
+    assert_ne!(player_position(&mut app), start);
+    let last_message = &app
+        .world
+        .resource::<MessageLog>()
+        .messages
+        .back()
+        .unwrap()
+        .text;
+    assert!(last_message.contains("stop exploring") || last_message.contains("nothing left"));
+}
+
+#[test]
+fn travel_follows_the_known_route() {
+    let mut app = headless_app();
+    app.insert_resource(NextRunSeed(5));
+    start_run(&mut app);
+    let start = player_position(&mut app);
+    let map = app.world.resource::<Map>();
+    let destination = (1..=6)
+        .flat_map(|x| (1..=6).map(move |y| (x, y)))
+        .map(|(x, y)| Position::new(start.x + x, start.y + y))
+        .filter(|position| map.known_path(start, *position).is_some())
+        .max_by_key(|position| position.distance(&start))
+        .unwrap();
+    app.world.resource_mut::<Travel>().start(destination);
+    for _ in 0..500 {
+        if app.world.resource::<Travel>().destination.is_none() {
+            break;
+        }
+        app.update();
+    }
+
+    assert!(app.world.resource::<Travel>().destination.is_none());
+    assert_eq!(player_position(&mut app), destination);
+}
+
+#[test]
+fn resting_waits_until_healed() {
+    let mut app = headless_app();
+    app.insert_resource(NextRunSeed(5));
+    start_run(&mut app);
+    let start = player_position(&mut app);
+    let turns = app.world.resource::<TurnCount>().0;
+    app.world
+        .query_filtered::<&mut Actor, With<Player>>()
+        .single_mut(&mut app.world)
+        .health = 95.;
+    app.world.resource_mut::<Rest>().start();
+    for _ in 0..1000 {
+        if !app.world.resource::<Rest>().active {
+            break;
+        }
+        app.update();
+    }
+
+    let actor = *app
+        .world
+        .query_filtered::<&Actor, With<Player>>()
+        .single(&app.world);
+    assert_eq!(actor.health, actor.max_health);
+    assert!(app.world.resource::<TurnCount>().0 > turns);
+    assert_eq!(player_position(&mut app), start);
+}
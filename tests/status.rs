@@ -0,0 +1,76 @@
+mod common;
+
+use common::{act, player, quiet_run};
+use roguelike::{
+    actor::Actor,
+    message_log::MessageLog,
+    player_action::PlayerAction,
+    status::{StatusEffects, StatusKind},
+};
+
+fn effect(status_effects: &StatusEffects, kind: StatusKind) -> (u32, u32) {
+    let effect = status_effects
+        .effects
+        .iter()
+        .find(|effect| effect.kind == kind)
+        .unwrap();
+    (effect.turns_left, effect.stacks)
+}
+
+#[test]
+fn poison_stacks_and_keeps_the_longer_duration() {
+    let mut status_effects = StatusEffects::default();
+    status_effects.apply(StatusKind::Poisoned, 5);
+    status_effects.apply(StatusKind::Poisoned, 3);
+    assert_eq!(effect(&status_effects, StatusKind::Poisoned), (5, 2));
+    status_effects.apply(StatusKind::Poisoned, 8);
+    assert_eq!(effect(&status_effects, StatusKind::Poisoned), (8, 3));
+    assert_eq!(status_effects.effects.len(), 1);
+}
+
+#[test]
+fn other_statuses_only_refresh() {
+    let mut status_effects = StatusEffects::default();
+    status_effects.apply(StatusKind::Burning, 3);
+    status_effects.apply(StatusKind::Burning, 5);
+    assert_eq!(effect(&status_effects, StatusKind::Burning), (5, 1));
+    status_effects.apply(StatusKind::Burning, 2);
+    assert_eq!(effect(&status_effects, StatusKind::Burning), (5, 1));
+}
+
+#[test]
+fn haste_and_slow_cancel_out() {
+    let mut status_effects = StatusEffects::default();
+    status_effects.apply(StatusKind::Hasted, 10);
+    status_effects.apply(StatusKind::Slowed, 10);
+    assert!(status_effects.effects.is_empty());
+
+    status_effects.apply(StatusKind::Slowed, 10);
+    assert!(status_effects.has(StatusKind::Slowed));
+    assert!(!status_effects.has(StatusKind::Hasted));
+}
+
+#[test]
+fn statuses_tick_down_and_expire() {
+    let mut app = quiet_run(3);
+    let player = player(&mut app);
+    let mut status_effects = app.world.get_mut::<StatusEffects>(player).unwrap();
+    status_effects.apply(StatusKind::Poisoned, 2);
+    status_effects.apply(StatusKind::Poisoned, 2);
+    status_effects.apply(StatusKind::Confused, 3);
+    let health = app.world.get::<Actor>(player).unwrap().health;
+
+    //two stacks of poison do two damage a turn
+    act(&mut app, PlayerAction::Wait);
+    assert_eq!(app.world.get::<Actor>(player).unwrap().health, health - 2.);
+    let status_effects = app.world.get::<StatusEffects>(player).unwrap();
+    assert_eq!(effect(status_effects, StatusKind::Poisoned), (1, 2));
+
+    act(&mut app, PlayerAction::Wait);
+    assert_eq!(app.world.get::<Actor>(player).unwrap().health, health - 4.);
+    let status_effects = app.world.get::<StatusEffects>(player).unwrap();
+    assert!(!status_effects.has(StatusKind::Poisoned));
+    assert!(status_effects.has(StatusKind::Confused));
+    let messages = &app.world.resource::<MessageLog>().messages;
+    assert_eq!(messages.back().unwrap().text, "You are no longer poisoned.");
+}